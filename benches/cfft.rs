@@ -0,0 +1,43 @@
+//! Batched circle FFT over the columns of a `TMat`.
+
+use divan::{counter::BytesCount, Bencher};
+use p3_circle::CircleDomain;
+use p3_matrix_layout_tests::{lanes, tiled_mat::TMat, tinym31::M31};
+use p3_mersenne_31::Mersenne31;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+
+fn main() {
+    println!("lanes: compiled {}", lanes::Backend::compiled());
+    divan::main();
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (16, 6), (20, 4)],
+    consts = [0,2,4],
+)]
+fn cfft_interpolate<const LTW: usize>(b: Bencher, (log_n, log_w): (usize, usize)) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let m = TMat::<M31, LTW>::from_fn(1 << log_n, 1 << log_w, |_, _| rng.gen());
+    let d = CircleDomain::<Mersenne31>::standard(log_n);
+
+    b.counter(BytesCount::new(m.bytes()))
+        .bench_local(|| m.cfft_interpolate(d));
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (16, 6), (20, 4)],
+    consts = [0,2,4],
+)]
+fn cfft_evaluate<const LTW: usize>(b: Bencher, (log_n, log_w): (usize, usize)) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let m = TMat::<M31, LTW>::from_fn(1 << log_n, 1 << log_w, |_, _| rng.gen());
+    let d = CircleDomain::<Mersenne31>::standard(log_n);
+
+    b.counter(BytesCount::new(m.bytes()))
+        .bench_local(|| m.cfft_evaluate(d));
+}
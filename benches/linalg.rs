@@ -0,0 +1,233 @@
+//! M31 linear algebra on tiles against Plonky3's `RowMajorMatrix`.
+
+use divan::{counter::BytesCount, Bencher};
+use p3_field::{
+    extension::{BinomialExtensionField, Complex},
+    AbstractField,
+};
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use p3_matrix_layout_tests::{lanes, linalg::Parallelism, tiled_mat::TMat, tinym31::M31};
+use p3_mersenne_31::Mersenne31;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaChaRng;
+use rayon::prelude::*;
+
+type Quartic = BinomialExtensionField<Complex<Mersenne31>, 2>;
+
+fn main() {
+    println!("lanes: compiled {}", lanes::Backend::compiled());
+    divan::main();
+}
+
+fn rand_vals(n: usize) -> Vec<M31> {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    (0..n).map(|_| rng.gen()).collect()
+}
+
+fn p3(xs: &[M31]) -> Vec<Mersenne31> {
+    xs.iter()
+        .map(|x| Mersenne31::from_canonical_u32(x.value()))
+        .collect()
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (16, 12)],
+    consts = [0,2,3,4],
+)]
+fn colwise_dot_product<const LTW: usize>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let (h, w) = (1 << log_h, 1 << log_w);
+    let vals = rand_vals(h * w);
+    let m = TMat::<M31, LTW>::from_fn(h, w, |r, c| vals[r * w + c]);
+    let v = rand_vals(h);
+
+    b.counter(BytesCount::new(m.bytes()))
+        .bench_local(|| m.columnwise_dot_product(&v));
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (16, 12)],
+)]
+fn colwise_dot_product_row_major(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let (h, w) = (1 << log_h, 1 << log_w);
+    let m = RowMajorMatrix::new(p3(&rand_vals(h * w)), w);
+    let v = p3(&rand_vals(h));
+
+    b.counter(BytesCount::of_slice(&m.values))
+        .bench_local(|| m.columnwise_dot_product(&v));
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (16, 12)],
+    consts = [0,2,4],
+)]
+fn dot_ext_powers<const LTW: usize>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let (h, w) = (1 << log_h, 1 << log_w);
+    let vals = rand_vals(h * w);
+    let m = TMat::<M31, LTW>::from_fn(h, w, |r, c| vals[r * w + c]);
+    let alpha: Quartic = ChaChaRng::seed_from_u64(1).gen();
+
+    b.counter(BytesCount::new(m.bytes()))
+        .bench_local(|| m.dot_ext_powers(alpha));
+}
+
+/// the complex extension, so there is a Plonky3 number to compare against
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (16, 12)],
+)]
+fn dot_ext_powers_complex(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let (h, w) = (1 << log_h, 1 << log_w);
+    let vals = rand_vals(h * w);
+    let m = TMat::<M31, 2>::from_fn(h, w, |r, c| vals[r * w + c]);
+    let alpha: Complex<Mersenne31> = ChaChaRng::seed_from_u64(1).gen();
+
+    b.counter(BytesCount::new(m.bytes()))
+        .bench_local(|| m.dot_ext_powers(alpha));
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (16, 12)],
+)]
+fn dot_ext_powers_complex_row_major(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let (h, w) = (1 << log_h, 1 << log_w);
+    let m = RowMajorMatrix::new(p3(&rand_vals(h * w)), w);
+    let alpha: Complex<Mersenne31> = ChaChaRng::seed_from_u64(1).gen();
+
+    b.counter(BytesCount::of_slice(&m.values))
+        .bench_local(|| m.dot_ext_powers(alpha).collect::<Vec<_>>());
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (16, 12)],
+    consts = [0,2,4],
+)]
+fn mat_vec_row_bands<const LTW: usize>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let (h, w) = (1 << log_h, 1 << log_w);
+    let vals = rand_vals(h * w);
+    let m = TMat::<M31, LTW>::from_fn(h, w, |r, c| vals[r * w + c]);
+    let v = rand_vals(w);
+
+    b.counter(BytesCount::new(m.bytes()))
+        .bench_local(|| m.mat_vec(&v, Parallelism::RowBands));
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (16, 12)],
+    consts = [0,2,4],
+)]
+fn mat_vec_col_bands<const LTW: usize>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let (h, w) = (1 << log_h, 1 << log_w);
+    let vals = rand_vals(h * w);
+    let m = TMat::<M31, LTW>::from_fn(h, w, |r, c| vals[r * w + c]);
+    let v = rand_vals(w);
+
+    b.counter(BytesCount::new(m.bytes()))
+        .bench_local(|| m.mat_vec(&v, Parallelism::ColBands));
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (16, 12)],
+)]
+fn mat_vec_row_major(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let (h, w) = (1 << log_h, 1 << log_w);
+    let m = RowMajorMatrix::new(p3(&rand_vals(h * w)), w);
+    let v = p3(&rand_vals(w));
+
+    b.counter(BytesCount::of_slice(&m.values)).bench_local(|| {
+        m.par_rows()
+            .map(|row| row.zip(&v).map(|(x, y)| x * *y).sum())
+            .collect::<Vec<Mersenne31>>()
+    });
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (16, 12)],
+    consts = [0,2,4],
+)]
+fn vec_mat_col_bands<const LTW: usize>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let (h, w) = (1 << log_h, 1 << log_w);
+    let vals = rand_vals(h * w);
+    let m = TMat::<M31, LTW>::from_fn(h, w, |r, c| vals[r * w + c]);
+    let v = rand_vals(h);
+
+    b.counter(BytesCount::new(m.bytes()))
+        .bench_local(|| m.vec_mat(&v, Parallelism::ColBands));
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [128, 512],
+    consts = [0,2,3,4],
+)]
+fn matmul<const LTW: usize>(b: Bencher, n: usize) {
+    let vals = rand_vals(2 * n * n);
+    let x = TMat::<M31, LTW>::from_fn(n, n, |r, c| vals[r * n + c]);
+    let y = TMat::<M31, LTW>::from_fn(n, n, |r, c| vals[(n + r) * n + c]);
+
+    b.bench_local(|| x.matmul(&y));
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [128, 512],
+)]
+fn matmul_naive(b: Bencher, n: usize) {
+    let vals = rand_vals(2 * n * n);
+    let (x, y) = vals.split_at(n * n);
+
+    b.bench_local(|| {
+        let mut out = vec![M31::ZERO; n * n];
+        for r in 0..n {
+            for c in 0..n {
+                out[r * n + c] = (0..n).map(|p| x[r * n + p] * y[p * n + c]).sum();
+            }
+        }
+        out
+    });
+}
+
+/// row by row, each output row a sum of scaled rows of the right-hand side
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [128, 512],
+)]
+fn matmul_row_major(b: Bencher, n: usize) {
+    let vals = rand_vals(2 * n * n);
+    let x = RowMajorMatrix::new(p3(&vals[..n * n]), n);
+    let y = RowMajorMatrix::new(p3(&vals[n * n..]), n);
+
+    b.bench_local(|| {
+        let mut out = RowMajorMatrix::new(vec![Mersenne31::zero(); n * n], n);
+        out.values
+            .par_chunks_exact_mut(n)
+            .enumerate()
+            .for_each(|(r, out_row)| {
+                for (p, a) in x.row(r).enumerate() {
+                    for (o, b) in out_row.iter_mut().zip(y.row(p)) {
+                        *o += a * b;
+                    }
+                }
+            });
+        out
+    });
+}
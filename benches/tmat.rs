@@ -1,12 +1,28 @@
-use std::arch::aarch64;
+use std::mem;
 
 use divan::{counter::BytesCount, Bencher};
 use itertools::izip;
-use p3_matrix_layout_tests::tiled_mat::{TMat, Tile};
+use p3_matrix_layout_tests::{
+    blocked::{BlockedMat, L2Mat, PageMat},
+    col_major::CmMat,
+    curve::{HilbertMat, MortonMat},
+    lanes,
+    layout::{self, MatrixLayout},
+    row_major::RmMat,
+    tile_map::{MappedMat, TileMap},
+    tiled_mat::{TMat, Tile},
+    tinym31::M31,
+};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaChaRng;
+use rayon::prelude::*;
 
 fn main() {
+    println!(
+        "lanes: compiled {}, dispatching to {}",
+        lanes::Backend::compiled(),
+        lanes::Backend::detect()
+    );
     divan::main();
 }
 
@@ -18,20 +34,294 @@ fn main() {
 )]
 fn fold_rows_u32_sum<const LTW: usize>(b: Bencher, (log_h, log_w): (usize, usize)) {
     let mut rng = ChaChaRng::seed_from_u64(0);
-    let m = TMat::<LTW>::from_fn(1 << log_h, 1 << log_w, |_, _| rng.gen());
+    let m = TMat::<u32, LTW>::from_fn(1 << log_h, 1 << log_w, |_, _| rng.gen());
 
     b.counter(BytesCount::new(m.bytes()))
         .with_inputs(|| m.clone())
         .bench_local_refs(|m| {
             m.fold_rows(
-                |_| Tile::<LTW>::zero(),
+                |_| Tile::<u32, LTW>::zero(),
                 |mut acc, tile| {
                     for (l, r) in izip!(acc.vecs_mut(), tile.vecs()) {
-                        *l = unsafe { aarch64::vpaddq_u32(*l, *r) };
-                        // *l = unsafe { aarch64::veorq_u32(*l, *r) };
+                        *l = lanes::add(*l, *r);
+                        // *l = lanes::xor(*l, *r);
                     }
                     acc
                 },
             );
         });
 }
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (18, 12)],
+    consts = [0,2,4],
+)]
+fn fold_cols_u32_sum<const LTW: usize>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let m = TMat::<u32, LTW>::from_fn(1 << log_h, 1 << log_w, |_, _| rng.gen());
+
+    b.counter(BytesCount::new(m.bytes()))
+        .with_inputs(|| m.clone())
+        .bench_local_refs(|m| {
+            m.fold_cols(
+                |_| Tile::<u32, LTW>::zero(),
+                |mut acc, tile| {
+                    acc.wrapping_add_assign(tile);
+                    acc
+                },
+            );
+        });
+}
+
+/// the same column sums over a plain row-major `Vec`, one 16-wide strip per task
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (18, 12)],
+)]
+fn col_sum_u32_row_major(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let w = 1 << log_w;
+    let vals: Vec<u32> = (0..(1 << (log_h + log_w))).map(|_| rng.gen()).collect();
+
+    b.counter(BytesCount::of_slice(&vals))
+        .with_inputs(|| vals.clone())
+        .bench_local_refs(|vals| {
+            (0..w / 16)
+                .into_par_iter()
+                .map(|strip| {
+                    let mut acc = [0u32; 16];
+                    for row in vals.chunks_exact(w) {
+                        for (a, x) in izip!(&mut acc, &row[strip * 16..][..16]) {
+                            *a = a.wrapping_add(*x);
+                        }
+                    }
+                    acc
+                })
+                .collect::<Vec<_>>()
+        });
+}
+
+/// so the generic benches can build any layout
+trait FromFn: MatrixLayout<M31> {
+    fn from_fn(h: usize, w: usize, f: impl FnMut(usize, usize) -> M31) -> Self;
+}
+
+impl FromFn for RmMat {
+    fn from_fn(h: usize, w: usize, f: impl FnMut(usize, usize) -> M31) -> Self {
+        RmMat::from_fn(h, w, f)
+    }
+}
+impl FromFn for CmMat {
+    fn from_fn(h: usize, w: usize, f: impl FnMut(usize, usize) -> M31) -> Self {
+        CmMat::from_fn(h, w, f)
+    }
+}
+impl<const LTW: usize> FromFn for TMat<M31, LTW> {
+    fn from_fn(h: usize, w: usize, f: impl FnMut(usize, usize) -> M31) -> Self {
+        TMat::from_fn(h, w, f)
+    }
+}
+impl<const LTW: usize, M: TileMap> FromFn for MappedMat<M31, LTW, M> {
+    fn from_fn(h: usize, w: usize, f: impl FnMut(usize, usize) -> M31) -> Self {
+        MappedMat::from_fn(h, w, f)
+    }
+}
+
+fn col_sums(m: &impl MatrixLayout<M31>) -> Vec<(usize, Vec<M31>)> {
+    m.fold_col_bands(
+        |cols| (cols.start, vec![M31::ZERO; cols.len()]),
+        |(c0, mut acc), block| {
+            let acc_cols = &mut acc[block.c0 - c0..][..block.width];
+            for i in 0..block.height {
+                for (j, a) in acc_cols.iter_mut().enumerate() {
+                    *a += block.get(i, j);
+                }
+            }
+            (c0, acc)
+        },
+    )
+}
+
+fn row_sums(m: &impl MatrixLayout<M31>) -> Vec<(usize, Vec<M31>)> {
+    m.fold_row_bands(
+        |rows| (rows.start, vec![M31::ZERO; rows.len()]),
+        |(r0, mut acc), block| {
+            let acc_rows = &mut acc[block.r0 - r0..][..block.height];
+            for (i, a) in acc_rows.iter_mut().enumerate() {
+                for j in 0..block.width {
+                    *a += block.get(i, j);
+                }
+            }
+            (r0, acc)
+        },
+    )
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (18, 12)],
+    types = [RmMat, CmMat, TMat<M31, 0>, TMat<M31, 2>, TMat<M31, 4>],
+)]
+fn layout_col_sums<L: FromFn>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let m = L::from_fn(1 << log_h, 1 << log_w, |_, _| rng.gen());
+
+    b.counter(BytesCount::new(mem::size_of::<M31>() << (log_h + log_w)))
+        .bench_local(|| col_sums(&m));
+}
+
+/// a row sweep then a column sweep: does a curve order help when both happen?
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (18, 12)],
+    types = [TMat<M31, 2>, MortonMat<M31, 2>, HilbertMat<M31, 2>, TMat<M31, 4>, MortonMat<M31, 4>],
+)]
+fn layout_row_and_col_sums<L: FromFn>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let m = L::from_fn(1 << log_h, 1 << log_w, |_, _| rng.gen());
+
+    // every element is read twice
+    let bytes = mem::size_of::<M31>() << (log_h + log_w + 1);
+    b.counter(BytesCount::new(bytes))
+        .bench_local(|| (row_sums(&m), col_sums(&m)));
+}
+
+/// the one generic kernel, instantiated for every layout
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (18, 12)],
+    types = [
+        RmMat,
+        CmMat,
+        TMat<M31, 2>,
+        TMat<M31, 4>,
+        MortonMat<M31, 2>,
+        HilbertMat<M31, 2>,
+        PageMat<M31, 2>,
+        BlockedMat<M31, 4, 6, 2, false, false>,
+    ],
+)]
+fn layout_colwise_dot_product<L: FromFn>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let m = L::from_fn(1 << log_h, 1 << log_w, |_, _| rng.gen());
+    let v: Vec<M31> = (0..1 << log_h).map(|_| rng.gen()).collect();
+
+    b.counter(BytesCount::new(mem::size_of::<M31>() << (log_h + log_w)))
+        .bench_local(|| layout::colwise_dot_product(&m, &v));
+}
+
+/// super-block size and order at the big size, where L2 and the TLB start to matter
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(18, 12)],
+    types = [
+        TMat<M31, 2>,
+        PageMat<M31, 2>,
+        L2Mat<M31, 2>,
+        BlockedMat<M31, 2, 3, 3, true, false>,
+        BlockedMat<M31, 2, 6, 6, false, true>,
+        BlockedMat<M31, 4, 6, 2, false, false>,
+    ],
+)]
+fn blocked_row_and_col_sums<L: FromFn>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let m = L::from_fn(1 << log_h, 1 << log_w, |_, _| rng.gen());
+
+    let bytes = mem::size_of::<M31>() << (log_h + log_w + 1);
+    b.counter(BytesCount::new(bytes))
+        .bench_local(|| (row_sums(&m), col_sums(&m)));
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (12, 12)],
+    consts = [0,2,3,4],
+)]
+fn transpose_u32<const LTW: usize>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let m = TMat::<u32, LTW>::from_fn(1 << log_h, 1 << log_w, |_, _| rng.gen());
+
+    b.counter(BytesCount::new(m.bytes()))
+        .bench_local(|| m.transpose());
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (12, 12)],
+)]
+fn transpose_u32_rows_to_cols(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let m = TMat::<u32, 4>::from_fn(1 << log_h, 1 << log_w, |_, _| rng.gen());
+
+    b.counter(BytesCount::new(m.bytes()))
+        .bench_local(|| m.transpose_reshaped::<0>());
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [10, 12],
+)]
+fn transpose_u32_in_place(b: Bencher, log_n: usize) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let m = TMat::<u32, 2>::from_fn(1 << log_n, 1 << log_n, |_, _| rng.gen());
+
+    b.counter(BytesCount::new(m.bytes()))
+        .with_inputs(|| m.clone())
+        .bench_local_refs(|m| m.transpose_in_place());
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (12, 12)],
+    consts = [0,1,2,3],
+)]
+fn retile_u32_from_rows<const O_LTW: usize>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let m = TMat::<u32, 4>::from_fn(1 << log_h, 1 << log_w, |_, _| rng.gen());
+
+    b.counter(BytesCount::new(m.bytes()))
+        .bench_local(|| m.retile::<O_LTW>());
+}
+
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (12, 12)],
+    consts = [0,1,2,3],
+)]
+fn retile_u32_to_rows<const I_LTW: usize>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let m = TMat::<u32, I_LTW>::from_fn(1 << log_h, 1 << log_w, |_, _| rng.gen());
+
+    b.counter(BytesCount::new(m.bytes()))
+        .bench_local(|| m.retile::<4>());
+}
+
+/// the same conversion through the streaming `par_row_tiles`, for comparison
+#[divan::bench(
+    min_time = 1, max_time = 5,
+    threads = false,
+    args = [(10, 8), (12, 12)],
+    consts = [0,2],
+)]
+fn retile_u32_streamed<const O_LTW: usize>(b: Bencher, (log_h, log_w): (usize, usize)) {
+    let mut rng = ChaChaRng::seed_from_u64(0);
+    let m = TMat::<u32, 4>::from_fn(1 << log_h, 1 << log_w, |_, _| rng.gen());
+
+    b.counter(BytesCount::new(m.bytes())).bench_local(|| {
+        m.par_row_tiles::<O_LTW>()
+            .flatten_iter()
+            .collect::<Vec<_>>()
+    });
+}
@@ -0,0 +1,145 @@
+//! Two-level tiling: 64-byte tiles grouped into super-blocks of `2^LBH x 2^LBW`
+//! tiles, so a sweep stays inside one page (or one L2-sized chunk) for a while
+//! before moving on.
+//!
+//! `COL_IN` picks column-major order for the tiles inside a block, `COL_OUT` for
+//! the blocks themselves; both default to row-major. The tile grid is padded out to
+//! whole blocks, and padding tiles are zero.
+
+use std::mem;
+
+use crate::{
+    packable::Packable,
+    tile_map::{MappedMat, TileMap},
+    tiled_mat::Tile,
+};
+
+/// `width` and `height` are logical, as in `TMat`.
+pub type BlockedMat<
+    T,
+    const LTW: usize,
+    const LBH: usize,
+    const LBW: usize,
+    const COL_IN: bool = false,
+    const COL_OUT: bool = false,
+> = MappedMat<T, LTW, BlockMap<LBH, LBW, COL_IN, COL_OUT>>;
+
+/// 8x8 tiles = 4 KiB, one page per block
+pub type PageMat<T, const LTW: usize> = BlockedMat<T, LTW, 3, 3>;
+/// 64x64 tiles = 256 KiB, roughly one L2 per block
+pub type L2Mat<T, const LTW: usize> = BlockedMat<T, LTW, 6, 6>;
+
+/// Where each tile goes: block by block, `COL_OUT` order between blocks and
+/// `COL_IN` order inside them.
+#[derive(Clone, Copy)]
+pub struct BlockMap<const LBH: usize, const LBW: usize, const COL_IN: bool, const COL_OUT: bool> {
+    /// in blocks
+    grid_height: usize,
+    grid_width: usize,
+}
+
+impl<const LBH: usize, const LBW: usize, const COL_IN: bool, const COL_OUT: bool>
+    BlockMap<LBH, LBW, COL_IN, COL_OUT>
+{
+    pub const TILES_PER_BLOCK: usize = 1 << (LBH + LBW);
+
+    fn block_index(&self, br: usize, bc: usize) -> usize {
+        if COL_OUT {
+            bc * self.grid_height + br
+        } else {
+            br * self.grid_width + bc
+        }
+    }
+}
+
+impl<const LBH: usize, const LBW: usize, const COL_IN: bool, const COL_OUT: bool> TileMap
+    for BlockMap<LBH, LBW, COL_IN, COL_OUT>
+{
+    fn new(tile_rows: usize, tiles_per_row: usize) -> Self {
+        Self {
+            grid_height: tile_rows.div_ceil(1 << LBH),
+            grid_width: tiles_per_row.div_ceil(1 << LBW),
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.grid_height * self.grid_width * Self::TILES_PER_BLOCK
+    }
+
+    fn tile_index(&self, tr: usize, tc: usize) -> usize {
+        let (ir, ic) = (tr & ((1 << LBH) - 1), tc & ((1 << LBW) - 1));
+        let inner = if COL_IN {
+            (ic << LBH) | ir
+        } else {
+            (ir << LBW) | ic
+        };
+        self.block_index(tr >> LBH, tc >> LBW) * Self::TILES_PER_BLOCK + inner
+    }
+}
+
+impl<
+        T: Packable,
+        const LTW: usize,
+        const LBH: usize,
+        const LBW: usize,
+        const COL_IN: bool,
+        const COL_OUT: bool,
+    > BlockedMat<T, LTW, LBH, LBW, COL_IN, COL_OUT>
+{
+    pub const TILES_PER_BLOCK: usize = BlockMap::<LBH, LBW, COL_IN, COL_OUT>::TILES_PER_BLOCK;
+    pub const BLOCK_BYTES: usize = Self::TILES_PER_BLOCK * mem::size_of::<Tile<T, LTW>>();
+
+    /// the tiles of block `(br, bc)`, in the block's inner order
+    pub fn block(&self, br: usize, bc: usize) -> &[Tile<T, LTW>] {
+        let i = self.map().block_index(br, bc) * Self::TILES_PER_BLOCK;
+        &self.tiles()[i..i + Self::TILES_PER_BLOCK]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn block_sizes() {
+        assert_eq!(PageMat::<u32, 2>::BLOCK_BYTES, 4096);
+        assert_eq!(L2Mat::<u32, 2>::BLOCK_BYTES, 256 * 1024);
+    }
+
+    fn check_map<const LBH: usize, const LBW: usize, const COL_IN: bool, const COL_OUT: bool>(
+        tile_rows: usize,
+        tiles_per_row: usize,
+    ) {
+        let map = BlockMap::<LBH, LBW, COL_IN, COL_OUT>::new(tile_rows, tiles_per_row);
+        // every real tile gets its own slot
+        let mut slots: Vec<usize> = (0..tile_rows)
+            .flat_map(|tr| (0..tiles_per_row).map(move |tc| (tr, tc)))
+            .map(|(tr, tc)| map.tile_index(tr, tc))
+            .collect();
+        slots.sort();
+        slots.dedup();
+        assert_eq!(slots.len(), tile_rows * tiles_per_row);
+        assert!(slots.iter().all(|&i| i < map.capacity()));
+    }
+
+    #[test]
+    fn maps_are_injective() {
+        for (tile_rows, tiles_per_row) in [(16, 16), (10, 3), (250, 10)] {
+            check_map::<0, 0, false, false>(tile_rows, tiles_per_row);
+            check_map::<2, 1, false, false>(tile_rows, tiles_per_row);
+            check_map::<2, 1, true, false>(tile_rows, tiles_per_row);
+            check_map::<1, 2, false, true>(tile_rows, tiles_per_row);
+            check_map::<3, 3, true, true>(tile_rows, tiles_per_row);
+        }
+    }
+
+    #[test]
+    fn inner_order() {
+        // 2x2 tiles per block, column-major inside: (1, 0) comes right after (0, 0)
+        let m = BlockedMat::<u32, 2, 1, 1, true, false>::zero(16, 16);
+        assert_eq!(m.tile_index(0, 0), 0);
+        assert_eq!(m.tile_index(1, 0), 1);
+        assert_eq!(m.tile_index(0, 1), 2);
+        assert_eq!(m.tile_index(0, 2), 4);
+    }
+}
@@ -0,0 +1,459 @@
+//! Batched circle FFT over the columns of an M31 `TMat`: the same transforms as
+//! `interp_simple` and `eval_simple`, but for every column at once.
+//!
+//! While a butterfly's two rows are in different tile rows, the butterfly runs on
+//! whole tiles: one row of twiddles is broadcast into a tile once per tile row and
+//! reused across it. The last `LTH` layers pair rows inside a tile, so they all run
+//! together in one pass over the tiles, along with the final scaling. Once a
+//! layer's blocks fit in cache, each block goes through every remaining layer
+//! before the next one starts.
+
+use std::ops::{Add, Mul, Sub};
+
+use itertools::izip;
+use p3_circle::CircleDomain;
+use p3_field::{batch_multiplicative_inverse, PrimeField32};
+use p3_mersenne_31::Mersenne31;
+use p3_util::reverse_slice_index_bits;
+use rayon::prelude::*;
+
+use crate::{
+    compute_twiddles,
+    packed_m31::PackedM31,
+    tiled_mat::{TMat, Tile},
+    tinym31::M31,
+};
+
+/// once a butterfly block is at most this many tiles (256 KiB, about an L2), one
+/// task takes it through all the remaining layers
+const CACHE_TILES: usize = 4096;
+
+/// the input row that the de-interleave puts at row `i`: the even rows first, then
+/// the odd ones backwards
+fn deinterleaved_src(i: usize, n: usize) -> usize {
+    if i < n / 2 {
+        2 * i
+    } else {
+        2 * n - 1 - 2 * i
+    }
+}
+
+/// the inverse of `deinterleaved_src`, for putting the rows back
+fn interleaved_src(i: usize, n: usize) -> usize {
+    if i & 1 == 0 {
+        i / 2
+    } else {
+        n - 1 - i / 2
+    }
+}
+
+/// `compute_twiddles`, bit-reversed and optionally inverted, one entry per layer
+/// from the biggest blocks down
+fn layer_twiddles(domain: CircleDomain<Mersenne31>, inverse: bool) -> Vec<Vec<M31>> {
+    compute_twiddles(domain)
+        .into_iter()
+        .map(|mut ts| {
+            if inverse {
+                ts = batch_multiplicative_inverse(&ts);
+            }
+            reverse_slice_index_bits(&mut ts);
+            ts.into_iter()
+                .map(|t| M31::from_canonical(t.as_canonical_u32()))
+                .collect()
+        })
+        .collect()
+}
+
+/// The two butterflies, usable on scalars and on packed lanes alike.
+trait Butterfly: Sync {
+    fn apply<X>(t: X, lo: X, hi: X) -> (X, X)
+    where
+        X: Copy + Add<Output = X> + Sub<Output = X> + Mul<Output = X>;
+}
+
+/// `lib::dif`, for interpolation
+struct Dif;
+/// `lib::dit`, for evaluation
+struct Dit;
+
+impl Butterfly for Dif {
+    #[inline(always)]
+    fn apply<X>(t: X, lo: X, hi: X) -> (X, X)
+    where
+        X: Copy + Add<Output = X> + Sub<Output = X> + Mul<Output = X>,
+    {
+        (lo + hi, t * (lo - hi))
+    }
+}
+
+impl Butterfly for Dit {
+    #[inline(always)]
+    fn apply<X>(t: X, lo: X, hi: X) -> (X, X)
+    where
+        X: Copy + Add<Output = X> + Sub<Output = X> + Mul<Output = X>,
+    {
+        let th = t * hi;
+        (lo + th, lo - th)
+    }
+}
+
+/// one butterfly per element, with `t` holding one twiddle per row
+#[inline]
+fn butterfly_tiles<B: Butterfly, const LTW: usize>(
+    t: &Tile<M31, LTW>,
+    lo: &mut Tile<M31, LTW>,
+    hi: &mut Tile<M31, LTW>,
+) {
+    for (l, h, t) in izip!(lo.packed_mut(), hi.packed_mut(), t.packed()) {
+        (*l, *h) = B::apply(*t, *l, *h);
+    }
+}
+
+/// the butterflies of one layer in one block of whole tile rows: tile row `j` of
+/// the first half pairs with tile row `j` of the second, using `ts[j << LTH..]`
+fn butterfly_block<B: Butterfly, const LTW: usize>(
+    blk: &mut [Tile<M31, LTW>],
+    ts: &[M31],
+    tpr: usize,
+    par: bool,
+) {
+    let lth = Tile::<M31, LTW>::LTH;
+    let (los, his) = blk.split_at_mut(blk.len() / 2);
+    let butterflies = |(j, (lo_row, hi_row)): (usize, (&mut [_], &mut [_]))| {
+        // one broadcast of this tile row's twiddles serves the whole row
+        let mut t = Tile::zero();
+        for (row, &x) in izip!(t.as_mut_slice().chunks_exact_mut(1 << LTW), &ts[j << lth..]) {
+            row.fill(x);
+        }
+        for (lo, hi) in izip!(lo_row, hi_row) {
+            butterfly_tiles::<B, LTW>(&t, lo, hi);
+        }
+    };
+    if par {
+        los.par_chunks_exact_mut(tpr)
+            .zip(his.par_chunks_exact_mut(tpr))
+            .enumerate()
+            .for_each(butterflies);
+    } else {
+        izip!(los.chunks_exact_mut(tpr), his.chunks_exact_mut(tpr))
+            .enumerate()
+            .for_each(butterflies);
+    }
+}
+
+/// one layer whose butterflies pair row `i` with row `i + ts.len()` of the same tile
+fn butterfly_in_tile<B: Butterfly, const LTW: usize>(tile: &mut Tile<M31, LTW>, ts: &[M31]) {
+    let half = ts.len() << LTW;
+    for blk in tile.as_mut_slice().chunks_exact_mut(2 * half) {
+        let (los, his) = blk.split_at_mut(half);
+        let rows = izip!(
+            los.chunks_exact_mut(1 << LTW),
+            his.chunks_exact_mut(1 << LTW)
+        );
+        for (&t, (lo_row, hi_row)) in ts.iter().zip(rows) {
+            for (l, h) in izip!(lo_row, hi_row) {
+                (*l, *h) = B::apply(t, *l, *h);
+            }
+        }
+    }
+}
+
+/// How a transform's layers split up for a matrix of `tpr` tiles per row.
+struct Layers<'a> {
+    /// layers with butterflies across tile rows, biggest blocks first
+    across: &'a [Vec<M31>],
+    /// layers inside a tile
+    within: &'a [Vec<M31>],
+    /// `across[..global]` have blocks too big for cache, so they go one layer at a
+    /// time over the whole matrix
+    global: usize,
+    /// tiles per cache-sized block, which runs through all the other layers at once
+    block: usize,
+    tpr: usize,
+}
+
+impl<'a> Layers<'a> {
+    fn new<const LTW: usize>(twiddles: &'a [Vec<M31>], tpr: usize) -> Self {
+        let lth = Tile::<M31, LTW>::LTH;
+        let split = twiddles
+            .iter()
+            .position(|ts| ts.len() < (1 << lth))
+            .unwrap_or(twiddles.len());
+        let (across, within) = twiddles.split_at(split);
+        let global = across
+            .iter()
+            .take_while(|ts| Self::block_tiles::<LTW>(ts, tpr) > CACHE_TILES)
+            .count();
+        let block = across
+            .get(global)
+            .map_or(tpr, |ts| Self::block_tiles::<LTW>(ts, tpr));
+        Self {
+            across,
+            within,
+            global,
+            block,
+            tpr,
+        }
+    }
+
+    fn block_tiles<const LTW: usize>(ts: &[M31], tpr: usize) -> usize {
+        2 * (ts.len() >> Tile::<M31, LTW>::LTH) * tpr
+    }
+
+    fn global_layer<B: Butterfly, const LTW: usize>(
+        &self,
+        tiles: &mut [Tile<M31, LTW>],
+        ts: &[M31],
+    ) {
+        tiles
+            .par_chunks_exact_mut(Self::block_tiles::<LTW>(ts, self.tpr))
+            .for_each(|blk| butterfly_block::<B, LTW>(blk, ts, self.tpr, true));
+    }
+
+    fn block_layer<B: Butterfly, const LTW: usize>(&self, blk: &mut [Tile<M31, LTW>], ts: &[M31]) {
+        for sub in blk.chunks_exact_mut(Self::block_tiles::<LTW>(ts, self.tpr)) {
+            butterfly_block::<B, LTW>(sub, ts, self.tpr, false);
+        }
+    }
+}
+
+impl<const LTW: usize> TMat<M31, LTW> {
+    /// A new matrix whose row `i` is row `src(i, height)` of this one.
+    fn permute_rows(&self, src: impl Fn(usize, usize) -> usize + Sync) -> Self {
+        let lth = Tile::<M31, LTW>::LTH;
+        let (n, tpr) = (self.height, self.tiles_per_row());
+        let mut out = TMat {
+            width: self.width,
+            height: self.height,
+            tiles: vec![Tile::zero(); self.tiles.len()],
+        };
+        if tpr == 0 {
+            return out;
+        }
+        // copy row segments into each output tile row
+        out.tiles
+            .par_chunks_exact_mut(tpr)
+            .enumerate()
+            .for_each(|(tr, row)| {
+                let r0 = tr << lth;
+                for rit in 0..(n - r0).min(1 << lth) {
+                    let s = src(r0 + rit, n);
+                    let srit = s & ((1 << lth) - 1);
+                    for (o, i) in row.iter_mut().zip(self.tile_row(s >> lth)) {
+                        o.as_mut_slice()[rit << LTW..][..1 << LTW]
+                            .copy_from_slice(&i.as_slice()[srit << LTW..][..1 << LTW]);
+                    }
+                }
+            });
+        out
+    }
+
+    fn check_domain(&self, domain: &CircleDomain<Mersenne31>) {
+        assert_eq!(
+            self.height,
+            domain.size(),
+            "can't transform {} rows over a domain of size {}",
+            self.height,
+            domain.size()
+        );
+    }
+
+    /// Interpolates every column from its evaluations over `domain`, in natural
+    /// order, to coefficients in the same basis and order as `interp_simple`.
+    pub fn cfft_interpolate(&self, domain: CircleDomain<Mersenne31>) -> Self {
+        self.check_domain(&domain);
+        let mut out = self.permute_rows(deinterleaved_src);
+        if out.tiles.is_empty() {
+            return out;
+        }
+        let twiddles = layer_twiddles(domain, true);
+        let layers = Layers::new::<LTW>(&twiddles, self.tiles_per_row());
+
+        for ts in &layers.across[..layers.global] {
+            layers.global_layer::<Dif, LTW>(&mut out.tiles, ts);
+        }
+        let n = domain.size() as u32;
+        let scale = PackedM31::splat(M31::from_canonical(n).inverse());
+        out.tiles
+            .par_chunks_exact_mut(layers.block)
+            .for_each(|blk| {
+                for ts in &layers.across[layers.global..] {
+                    layers.block_layer::<Dif, LTW>(blk, ts);
+                }
+                for tile in blk {
+                    for ts in layers.within {
+                        butterfly_in_tile::<Dif, LTW>(tile, ts);
+                    }
+                    for x in tile.packed_mut() {
+                        *x *= scale;
+                    }
+                }
+            });
+        out
+    }
+
+    /// Evaluates every column, as coefficients from `cfft_interpolate`, over
+    /// `domain` in natural order: the same layers run backwards with `dit`, then
+    /// the rows are interleaved again.
+    pub fn cfft_evaluate(&self, domain: CircleDomain<Mersenne31>) -> Self {
+        self.check_domain(&domain);
+        if self.tiles.is_empty() {
+            return self.clone();
+        }
+        let mut work = self.clone();
+        let twiddles = layer_twiddles(domain, false);
+        let layers = Layers::new::<LTW>(&twiddles, self.tiles_per_row());
+
+        work.tiles
+            .par_chunks_exact_mut(layers.block)
+            .for_each(|blk| {
+                for tile in blk.iter_mut() {
+                    for ts in layers.within.iter().rev() {
+                        butterfly_in_tile::<Dit, LTW>(tile, ts);
+                    }
+                }
+                for ts in layers.across[layers.global..].iter().rev() {
+                    layers.block_layer::<Dit, LTW>(blk, ts);
+                }
+            });
+        for ts in layers.across[..layers.global].iter().rev() {
+            layers.global_layer::<Dit, LTW>(&mut work.tiles, ts);
+        }
+        work.permute_rows(interleaved_src)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_circle::CircleEvaluations;
+    use p3_field::AbstractField;
+    use p3_matrix::dense::RowMajorMatrix;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+    use crate::{circle_basis, eval_simple, interp_simple};
+
+    fn p3(x: M31) -> Mersenne31 {
+        Mersenne31::from_canonical_u32(x.value())
+    }
+
+    fn check_interpolate<const LTW: usize>(log_n: usize, w: usize) {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let n = 1 << log_n;
+        let vals: Vec<M31> = (0..n * w).map(|_| rng.gen()).collect();
+        let d = CircleDomain::<Mersenne31>::standard(log_n);
+
+        let m = TMat::<M31, LTW>::from_fn(n, w, |r, c| vals[r * w + c]);
+        let coeffs = m.cfft_interpolate(d);
+        let expected = CircleEvaluations::from_natural_order(
+            d,
+            RowMajorMatrix::new(vals.iter().copied().map(p3).collect(), w),
+        )
+        .interpolate();
+        let expected = TMat::<M31, LTW>::from_fn(n, w, |r, c| {
+            M31::from_canonical(expected.values[r * w + c].as_canonical_u32())
+        });
+        // comparing whole tiles also checks the padding stays zero
+        assert!(
+            coeffs.tiles == expected.tiles,
+            "LTW = {LTW}, log_n = {log_n}, w = {w}"
+        );
+    }
+
+    #[test]
+    fn interpolate_matches_p3() {
+        for log_n in 1..=5 {
+            for w in [1, 5, 16, 37] {
+                check_interpolate::<0>(log_n, w);
+                check_interpolate::<2>(log_n, w);
+                check_interpolate::<3>(log_n, w);
+                check_interpolate::<4>(log_n, w);
+            }
+        }
+    }
+
+    fn check_against_simple<const LTW: usize>(log_n: usize, w: usize) {
+        let mut rng = ChaChaRng::seed_from_u64(1);
+        let d = CircleDomain::<Mersenne31>::standard(log_n);
+        let m = TMat::<M31, LTW>::from_fn(1 << log_n, w, |_, _| rng.gen());
+        let coeffs = m.cfft_interpolate(d);
+        for c in 0..w {
+            let col = m.col(c).map(p3).collect();
+            let expected = interp_simple(col, compute_twiddles(d));
+            assert_eq!(
+                coeffs.col(c).map(p3).collect::<Vec<_>>(),
+                expected,
+                "LTW = {LTW}, column {c}"
+            );
+        }
+    }
+
+    #[test]
+    fn interpolate_matches_simple() {
+        // big enough that the first layers run over the whole matrix
+        check_against_simple::<0>(14, 20);
+        check_against_simple::<2>(14, 9);
+        check_against_simple::<3>(14, 20);
+        check_against_simple::<4>(14, 20);
+    }
+
+    fn check_evaluate<const LTW: usize>(log_n: usize, w: usize) {
+        let mut rng = ChaChaRng::seed_from_u64(2);
+        let d = CircleDomain::<Mersenne31>::standard(log_n);
+        let coeffs = TMat::<M31, LTW>::from_fn(1 << log_n, w, |_, _| rng.gen());
+        let evals = coeffs.cfft_evaluate(d);
+        for (r, pt) in d.points().enumerate() {
+            let basis = circle_basis(pt, log_n);
+            for c in 0..w {
+                let expected: Mersenne31 =
+                    coeffs.col(c).map(p3).zip(&basis).map(|(x, b)| x * *b).sum();
+                assert_eq!(
+                    p3(evals.get(r, c)),
+                    expected,
+                    "LTW = {LTW}, log_n = {log_n}, ({r}, {c})"
+                );
+            }
+        }
+        let col = coeffs.col(0).map(p3).collect();
+        let expected = eval_simple(col, compute_twiddles(d));
+        assert_eq!(evals.col(0).map(p3).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn evaluate_matches_basis() {
+        for log_n in [1, 2, 5, 8] {
+            for w in [1, 5, 16, 37] {
+                check_evaluate::<0>(log_n, w);
+                check_evaluate::<2>(log_n, w);
+                check_evaluate::<3>(log_n, w);
+                check_evaluate::<4>(log_n, w);
+            }
+        }
+    }
+
+    fn check_round_trip<const LTW: usize>(log_n: usize, w: usize) {
+        let mut rng = ChaChaRng::seed_from_u64(3);
+        let d = CircleDomain::<Mersenne31>::standard(log_n);
+        let m = TMat::<M31, LTW>::from_fn(1 << log_n, w, |_, _| rng.gen());
+        let back = m.cfft_interpolate(d).cfft_evaluate(d);
+        assert!(
+            back.tiles == m.tiles,
+            "LTW = {LTW}, log_n = {log_n}, w = {w}"
+        );
+    }
+
+    #[test]
+    fn evaluate_inverts_interpolate() {
+        for log_n in 1..=20 {
+            // a single column is exactly one tile wide here
+            check_round_trip::<0>(log_n, 1);
+            if log_n <= 13 {
+                // wide enough at the top to go through the whole-matrix layers
+                check_round_trip::<2>(log_n, 9);
+                check_round_trip::<3>(log_n, 20);
+                check_round_trip::<4>(log_n, 20);
+            }
+        }
+    }
+}
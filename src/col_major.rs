@@ -0,0 +1,341 @@
+//! Plain column-major storage: the other extreme from `RmMat`, and the natural
+//! layout for colwise work.
+
+use std::{cmp, fmt, ops::Range};
+
+use rayon::{iter::Either, prelude::*};
+
+use crate::{
+    packable::Packable,
+    row_major::{RmMat, CHUNK},
+    tiled_mat::{TMat, Tile},
+    tinym31::M31,
+};
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct CmMat {
+    h: usize,
+    w: usize,
+    vals: Vec<M31>,
+}
+
+impl CmMat {
+    pub fn zero(height: usize, width: usize) -> Self {
+        Self {
+            h: height,
+            w: width,
+            vals: vec![M31::ZERO; height * width],
+        }
+    }
+
+    pub fn from_fn(height: usize, width: usize, mut f: impl FnMut(usize, usize) -> M31) -> Self {
+        let mut vals = Vec::with_capacity(height * width);
+        for c in 0..width {
+            vals.extend((0..height).map(|r| f(r, c)));
+        }
+        Self {
+            h: height,
+            w: width,
+            vals,
+        }
+    }
+
+    /// `vals` holds the columns back to back
+    pub fn new(height: usize, width: usize, vals: Vec<M31>) -> Self {
+        assert_eq!(vals.len(), height * width, "{height}x{width} matrix");
+        Self {
+            h: height,
+            w: width,
+            vals,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.w
+    }
+
+    pub fn height(&self) -> usize {
+        self.h
+    }
+
+    pub fn values(&self) -> &[M31] {
+        &self.vals
+    }
+
+    pub fn bytes(&self) -> usize {
+        std::mem::size_of_val(self.vals.as_slice())
+    }
+
+    pub fn get(&self, r: usize, c: usize) -> M31 {
+        self.vals[self.index(r, c)]
+    }
+
+    pub fn set(&mut self, r: usize, c: usize, val: M31) {
+        let i = self.index(r, c);
+        self.vals[i] = val;
+    }
+
+    fn index(&self, r: usize, c: usize) -> usize {
+        let (h, w) = (self.h, self.w);
+        assert!(
+            r < h && c < w,
+            "({r}, {c}) out of bounds for {h}x{w} matrix"
+        );
+        c * h + r
+    }
+
+    pub fn col(&self, c: usize) -> &[M31] {
+        let w = self.w;
+        assert!(c < w, "column {c} out of bounds for a matrix of width {w}");
+        &self.vals[c * self.h..][..self.h]
+    }
+
+    pub fn par_cols(&self) -> impl IndexedParallelIterator<Item = &[M31]> {
+        (0..self.w).into_par_iter().map(|c| self.col(c))
+    }
+
+    /// a zero-height matrix has no values to chunk, but still has a column per column
+    pub fn par_cols_mut(&mut self) -> impl IndexedParallelIterator<Item = &mut [M31]> {
+        if self.h == 0 {
+            Either::Left((0..self.w).into_par_iter().map(|_| <&mut [M31]>::default()))
+        } else {
+            Either::Right(self.vals.par_chunks_exact_mut(self.h))
+        }
+    }
+
+    /// One accumulator per `CHUNK`-tall row strip, fed each column's piece of the
+    /// strip left to right. `init` gets the row range of the strip.
+    pub fn fold_rows<Acc, Init, Op>(&self, init: Init, op: Op) -> Vec<Acc>
+    where
+        Acc: Send + Sync,
+        Init: Fn(Range<usize>) -> Acc + Send + Sync,
+        Op: Fn(Acc, &[M31]) -> Acc + Send + Sync,
+    {
+        let h = self.h;
+        (0..h.div_ceil(CHUNK))
+            .into_par_iter()
+            .map(|strip| {
+                let rows = strip * CHUNK..cmp::min((strip + 1) * CHUNK, h);
+                self.vals
+                    .chunks_exact(h)
+                    .map(|col| &col[rows.clone()])
+                    .fold(init(rows.clone()), &op)
+            })
+            .collect()
+    }
+
+    /// One accumulator per column, fed the column top to bottom in `CHUNK`-tall
+    /// pieces (the last one may be short). `init` gets the column as a one-column
+    /// range.
+    pub fn fold_cols<Acc, Init, Op>(&self, init: Init, op: Op) -> Vec<Acc>
+    where
+        Acc: Send + Sync,
+        Init: Fn(Range<usize>) -> Acc + Send + Sync,
+        Op: Fn(Acc, &[M31]) -> Acc + Send + Sync,
+    {
+        self.par_cols()
+            .enumerate()
+            .map(|(c, col)| col.chunks(CHUNK).fold(init(c..c + 1), &op))
+            .collect()
+    }
+}
+
+/// Writes the transpose of the `rows` x `cols` row-major `src` into `dst`, one
+/// `CHUNK`-wide strip of `src` columns per task and `CHUNK` x `CHUNK` blocks within
+/// it, so neither side is walked with a huge stride for long.
+fn transpose_into<T: Packable>(src: &[T], rows: usize, cols: usize, dst: &mut [T]) {
+    assert_eq!((src.len(), dst.len()), (rows * cols, rows * cols));
+    if rows == 0 || cols == 0 {
+        return;
+    }
+    dst.par_chunks_mut(rows * CHUNK)
+        .enumerate()
+        .for_each(|(strip, out)| {
+            let c0 = strip * CHUNK;
+            for r0 in (0..rows).step_by(CHUNK) {
+                for (dc, out_row) in out.chunks_exact_mut(rows).enumerate() {
+                    for r in r0..cmp::min(r0 + CHUNK, rows) {
+                        out_row[r] = src[r * cols + c0 + dc];
+                    }
+                }
+            }
+        });
+}
+
+impl From<&RmMat> for CmMat {
+    fn from(m: &RmMat) -> Self {
+        let (h, w) = (m.height(), m.width());
+        let mut vals = vec![M31::ZERO; h * w];
+        transpose_into(m.values(), h, w, &mut vals);
+        CmMat::new(h, w, vals)
+    }
+}
+
+impl From<&CmMat> for RmMat {
+    fn from(m: &CmMat) -> Self {
+        let (h, w) = (m.h, m.w);
+        let mut vals = vec![M31::ZERO; h * w];
+        transpose_into(&m.vals, w, h, &mut vals);
+        RmMat::new(h, w, vals)
+    }
+}
+
+impl<const LTW: usize> From<&TMat<M31, LTW>> for CmMat {
+    fn from(m: &TMat<M31, LTW>) -> Self {
+        let lth = Tile::<M31, LTW>::LTH;
+        let (h, w) = (m.height, m.width);
+        let mut vals = vec![M31::ZERO; h * w];
+        if h > 0 {
+            // each tile column fills (1 << LTW) whole columns, `CHUNK` rows at a
+            // time so the tiles stay in cache while each column gets a run
+            vals.par_chunks_mut(h << LTW)
+                .enumerate()
+                .for_each(|(tc, strip)| {
+                    for r0 in (0..h).step_by(CHUNK) {
+                        let r1 = cmp::min(r0 + CHUNK, h);
+                        for (cit, col) in strip.chunks_exact_mut(h).enumerate() {
+                            for (i, run) in col[r0..r1].chunks_mut(1 << lth).enumerate() {
+                                let tile = m.tile_row((r0 >> lth) + i)[tc].as_slice();
+                                if LTW == 0 {
+                                    run.copy_from_slice(&tile[..run.len()]);
+                                } else {
+                                    let tile_col = tile[cit..].iter().step_by(1 << LTW);
+                                    for (x, y) in run.iter_mut().zip(tile_col) {
+                                        *x = *y;
+                                    }
+                                }
+                            }
+                        }
+                    }
+                });
+        }
+        CmMat::new(h, w, vals)
+    }
+}
+
+impl<const LTW: usize> From<&CmMat> for TMat<M31, LTW> {
+    fn from(m: &CmMat) -> Self {
+        let lth = Tile::<M31, LTW>::LTH;
+        let (h, w) = (m.h, m.w);
+        let tpr = w.div_ceil(1 << LTW);
+        let mut tiles = vec![Tile::zero(); h.div_ceil(1 << lth) * tpr];
+        if tpr > 0 {
+            tiles
+                .par_chunks_exact_mut(tpr)
+                .enumerate()
+                .for_each(|(tr, tile_row)| {
+                    for (tc, tile) in tile_row.iter_mut().enumerate() {
+                        let (r0, c0) = (tr << lth, tc << LTW);
+                        *tile = Tile::from_fn_clipped(h - r0, w - c0, |rit, cit| {
+                            m.vals[(c0 + cit) * h + r0 + rit]
+                        });
+                    }
+                });
+        }
+        TMat {
+            width: w,
+            height: h,
+            tiles,
+        }
+    }
+}
+
+impl fmt::Debug for CmMat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for r in 0..self.h {
+            for c in 0..self.w {
+                write!(f, "{:?} ", self.get(r, c))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+
+    fn rand_rm(h: usize, w: usize) -> RmMat {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        RmMat::from_fn(h, w, |_, _| rng.gen())
+    }
+
+    #[test]
+    fn access_and_folds() {
+        let (h, w) = (70, 37);
+        let rm = rand_rm(h, w);
+        let mut cm = CmMat::from_fn(h, w, |r, c| rm.get(r, c));
+        assert_eq!((cm.height(), cm.width()), (h, w));
+        for c in 0..w {
+            assert!(cm.col(c).iter().copied().eq((0..h).map(|r| rm.get(r, c))));
+        }
+
+        let col_prods = cm.fold_cols(|_| M31::ONE, |acc, xs| acc * xs.iter().copied().product());
+        let expected: Vec<M31> = (0..w)
+            .map(|c| (0..h).map(|r| rm.get(r, c)).product())
+            .collect();
+        assert_eq!(col_prods, expected);
+
+        let strips = cm.fold_rows(
+            |rows| (rows.clone(), vec![M31::ZERO; rows.len()]),
+            |(rows, mut acc), xs| {
+                for (a, x) in acc.iter_mut().zip(xs) {
+                    *a += *x;
+                }
+                (rows, acc)
+            },
+        );
+        let row_sums: Vec<M31> = strips.into_iter().flat_map(|(_, acc)| acc).collect();
+        let expected: Vec<M31> = (0..h).map(|r| rm.row(r).iter().copied().sum()).collect();
+        assert_eq!(row_sums, expected);
+
+        cm.set(h - 1, 0, M31::TWO);
+        assert_eq!(cm.get(h - 1, 0), M31::TWO);
+    }
+
+    #[test]
+    fn row_major_round_trip() {
+        for (h, w) in [(64, 64), (70, 37), (1, 100), (100, 1), (0, 5), (5, 0)] {
+            let rm = rand_rm(h, w);
+            let cm = CmMat::from(&rm);
+            assert_eq!(cm, CmMat::from_fn(h, w, |r, c| rm.get(r, c)));
+            assert_eq!(RmMat::from(&cm), rm);
+        }
+    }
+
+    #[test]
+    fn zero_dimensions() {
+        for (h, w) in [(0, 5), (5, 0), (0, 0)] {
+            let mut cm = CmMat::zero(h, w);
+            assert_eq!(cm.par_cols().count(), w);
+            assert_eq!(cm.par_cols_mut().count(), w);
+            assert_eq!(cm.fold_cols(|cols| cols, |cols, _| cols).len(), w);
+            let strips = cm.fold_rows(|rows| rows, |rows, _| rows);
+            assert_eq!(strips.len(), h.div_ceil(CHUNK));
+        }
+    }
+
+    fn check_tmat_round_trip<const LTW: usize>(h: usize, w: usize) {
+        let rm = rand_rm(h, w);
+        let tm = TMat::<M31, LTW>::from_fn(h, w, |r, c| rm.get(r, c));
+        let cm = CmMat::from(&tm);
+        assert_eq!(cm, CmMat::from(&rm), "LTW = {LTW}, {h}x{w}");
+        assert_eq!(TMat::<M31, LTW>::from(&cm).tiles, tm.tiles);
+    }
+
+    #[test]
+    fn tmat_round_trip() {
+        check_tmat_round_trip::<0>(64, 64);
+        check_tmat_round_trip::<2>(64, 64);
+        check_tmat_round_trip::<4>(64, 64);
+        check_tmat_round_trip::<1>(70, 37);
+        check_tmat_round_trip::<3>(70, 37);
+        check_tmat_round_trip::<4>(1000, 37);
+        check_tmat_round_trip::<2>(0, 5);
+        check_tmat_round_trip::<2>(5, 0);
+    }
+}
@@ -0,0 +1,254 @@
+//! Tiles laid out along a space-filling curve instead of row-major, so tiles that
+//! are close in 2D stay close in memory whichever way you sweep.
+//!
+//! The tile grid is padded up to `2^kr x 2^kc` tiles and cut into `2^k x 2^k`
+//! squares, `k = min(kr, kc)`. The squares are stored one after another along the
+//! long side, and the curve ([`Morton`] or [`Hilbert`]) orders tiles within each
+//! square. Tiles in the padding are zero.
+
+use std::{cmp, marker::PhantomData, mem};
+
+use crate::tile_map::{MappedMat, TileMap};
+
+/// Orders the tiles of a `2^k x 2^k` square.
+pub trait TileOrder: Send + Sync + 'static {
+    /// position of tile `(tr, tc)` along the curve
+    fn index(k: u32, tr: usize, tc: usize) -> usize;
+    /// inverse of `index`
+    fn coords(k: u32, i: usize) -> (usize, usize);
+}
+
+/// Z-order: interleave the bits, column bit lowest.
+pub struct Morton;
+
+/// spreads the low 32 bits of `x` out to the even bits
+fn spread(x: usize) -> usize {
+    let mut x = x as u64 & 0xffff_ffff;
+    x = (x | (x << 16)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x << 8)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x << 4)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x << 2)) & 0x3333_3333_3333_3333;
+    x = (x | (x << 1)) & 0x5555_5555_5555_5555;
+    x as usize
+}
+
+/// inverse of `spread`
+fn compact(x: usize) -> usize {
+    let mut x = x as u64 & 0x5555_5555_5555_5555;
+    x = (x | (x >> 1)) & 0x3333_3333_3333_3333;
+    x = (x | (x >> 2)) & 0x0f0f_0f0f_0f0f_0f0f;
+    x = (x | (x >> 4)) & 0x00ff_00ff_00ff_00ff;
+    x = (x | (x >> 8)) & 0x0000_ffff_0000_ffff;
+    x = (x | (x >> 16)) & 0x0000_0000_ffff_ffff;
+    x as usize
+}
+
+pub fn morton_encode(tr: usize, tc: usize) -> usize {
+    spread(tc) | (spread(tr) << 1)
+}
+
+pub fn morton_decode(i: usize) -> (usize, usize) {
+    (compact(i >> 1), compact(i))
+}
+
+impl TileOrder for Morton {
+    fn index(_k: u32, tr: usize, tc: usize) -> usize {
+        morton_encode(tr, tc)
+    }
+    fn coords(_k: u32, i: usize) -> (usize, usize) {
+        morton_decode(i)
+    }
+}
+
+/// Hilbert curve: like Z-order but consecutive tiles always share an edge.
+pub struct Hilbert;
+
+/// reflect/rotate a quadrant so the sub-curve has the right orientation
+fn hilbert_rot(s: usize, x: &mut usize, y: &mut usize, rx: usize, ry: usize) {
+    if ry == 0 {
+        if rx == 1 {
+            *x = s - 1 - *x;
+            *y = s - 1 - *y;
+        }
+        mem::swap(x, y);
+    }
+}
+
+pub fn hilbert_encode(k: u32, tr: usize, tc: usize) -> usize {
+    let n = 1 << k;
+    let (mut x, mut y, mut i) = (tc, tr, 0);
+    let mut s = n >> 1;
+    while s > 0 {
+        let rx = usize::from(x & s != 0);
+        let ry = usize::from(y & s != 0);
+        i += s * s * ((3 * rx) ^ ry);
+        hilbert_rot(n, &mut x, &mut y, rx, ry);
+        s >>= 1;
+    }
+    i
+}
+
+pub fn hilbert_decode(k: u32, i: usize) -> (usize, usize) {
+    let (mut x, mut y, mut t) = (0, 0, i);
+    let mut s = 1;
+    while s < (1 << k) {
+        let rx = 1 & (t >> 1);
+        let ry = 1 & (t ^ rx);
+        hilbert_rot(s, &mut x, &mut y, rx, ry);
+        x += s * rx;
+        y += s * ry;
+        t >>= 2;
+        s <<= 1;
+    }
+    (y, x)
+}
+
+impl TileOrder for Hilbert {
+    fn index(k: u32, tr: usize, tc: usize) -> usize {
+        hilbert_encode(k, tr, tc)
+    }
+    fn coords(k: u32, i: usize) -> (usize, usize) {
+        hilbert_decode(k, i)
+    }
+}
+
+/// `width` and `height` are logical, as in `TMat`.
+///
+/// The curve covers tile rows and tiles per row each rounded up to a power of
+/// two, and storage is allocated for all of it, padding tiles included. That is
+/// close to 4x the tiles just past a power of two: 257x257 M31s in 4x4 tiles
+/// has 65x65 tiles but stores 128x128.
+pub type CurveMat<T, const LTW: usize, O = Morton> = MappedMat<T, LTW, CurveMap<O>>;
+
+/// Z-ordered tiles; pads like `CurveMat`.
+pub type MortonMat<T, const LTW: usize> = CurveMat<T, LTW, Morton>;
+/// Hilbert-ordered tiles; pads like `CurveMat`.
+pub type HilbertMat<T, const LTW: usize> = CurveMat<T, LTW, Hilbert>;
+
+/// Where each tile goes: squares along the long side, `O` within a square.
+pub struct CurveMap<O> {
+    /// log2 of the side of a curve square, in tiles
+    k: u32,
+    /// number of squares along the long side
+    squares: usize,
+    /// squares stacked vertically rather than side by side
+    tall: bool,
+    _order: PhantomData<O>,
+}
+
+impl<O> Clone for CurveMap<O> {
+    fn clone(&self) -> Self {
+        Self {
+            _order: PhantomData,
+            ..*self
+        }
+    }
+}
+
+impl<O: TileOrder> CurveMap<O> {
+    /// inverse of `tile_index`, also defined for padding tiles
+    pub fn tile_coords(&self, i: usize) -> (usize, usize) {
+        let k = self.k;
+        let square = i >> (2 * k);
+        let (tr, tc) = O::coords(k, i & ((1 << (2 * k)) - 1));
+        if self.tall {
+            (tr | (square << k), tc)
+        } else {
+            (tr, tc | (square << k))
+        }
+    }
+}
+
+impl<O: TileOrder> TileMap for CurveMap<O> {
+    fn new(tile_rows: usize, tiles_per_row: usize) -> Self {
+        let kr = tile_rows.next_power_of_two().ilog2();
+        let kc = tiles_per_row.next_power_of_two().ilog2();
+        Self {
+            k: cmp::min(kr, kc),
+            squares: 1 << kr.abs_diff(kc),
+            tall: kr > kc,
+            _order: PhantomData,
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.squares << (2 * self.k)
+    }
+
+    fn tile_index(&self, tr: usize, tc: usize) -> usize {
+        let k = self.k;
+        let mask = (1 << k) - 1;
+        // the grid is one square wide or one square tall, so at most one of these
+        // is nonzero
+        let square = (tr >> k) | (tc >> k);
+        (square << (2 * k)) | O::index(k, tr & mask, tc & mask)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn curves_are_bijections() {
+        for k in 0..5 {
+            let n = 1 << k;
+            let mut seen = HashSet::new();
+            for tr in 0..n {
+                for tc in 0..n {
+                    for (i, coords) in [
+                        (
+                            Morton::index(k, tr, tc),
+                            Morton::coords(k, Morton::index(k, tr, tc)),
+                        ),
+                        (
+                            Hilbert::index(k, tr, tc),
+                            Hilbert::coords(k, Hilbert::index(k, tr, tc)),
+                        ),
+                    ] {
+                        assert!(i < n * n);
+                        assert_eq!(coords, (tr, tc));
+                    }
+                    seen.insert(Hilbert::index(k, tr, tc));
+                }
+            }
+            assert_eq!(seen.len(), n * n);
+        }
+        assert_eq!(morton_encode(0b11, 0b01), 0b1011);
+    }
+
+    #[test]
+    fn hilbert_steps_are_adjacent() {
+        let k = 4;
+        for i in 1..(1 << (2 * k)) {
+            let (r0, c0) = hilbert_decode(k, i - 1);
+            let (r1, c1) = hilbert_decode(k, i);
+            assert_eq!(r0.abs_diff(r1) + c0.abs_diff(c1), 1, "step {i}");
+        }
+    }
+
+    fn check_map<O: TileOrder>(tile_rows: usize, tiles_per_row: usize) {
+        let map = CurveMap::<O>::new(tile_rows, tiles_per_row);
+        let mut seen = HashSet::new();
+        for i in 0..map.capacity() {
+            let (tr, tc) = map.tile_coords(i);
+            assert_eq!(map.tile_index(tr, tc), i);
+            seen.insert((tr, tc));
+        }
+        for tr in 0..tile_rows {
+            for tc in 0..tiles_per_row {
+                assert!(seen.contains(&(tr, tc)), "({tr}, {tc}) has no slot");
+            }
+        }
+    }
+
+    #[test]
+    fn maps_cover_the_grid() {
+        for (tile_rows, tiles_per_row) in [(1, 1), (4, 4), (10, 3), (2, 75), (250, 3), (0, 3)] {
+            check_map::<Morton>(tile_rows, tiles_per_row);
+            check_map::<Hilbert>(tile_rows, tiles_per_row);
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Glue between `TMat` and Plonky3's matrix types.
+
+use std::{array, ops::Deref};
+
+use itertools::Either;
+use p3_field::PackedValue;
+use p3_matrix::{dense::RowMajorMatrix, Matrix};
+use rayon::prelude::*;
+
+use crate::{
+    packable::Packable,
+    tiled_mat::{RowIter, TMat, Tile},
+};
+
+/// The widest `PackedValue` the packed rows handle: Plonky3's AVX-512 packings
+/// are 16 wide.
+const MAX_PACKED_WIDTH: usize = 16;
+
+impl<T: Packable, const LTW: usize> From<RowMajorMatrix<T>> for TMat<T, LTW> {
+    fn from(m: RowMajorMatrix<T>) -> Self {
+        let lth = Tile::<T, LTW>::LTH;
+        let (h, w) = (m.height(), m.width());
+        let tpr = w.div_ceil(1 << LTW);
+        let tile_rows = h.div_ceil(1 << lth);
+
+        let mut tiles = vec![Tile::zero(); tile_rows * tpr];
+        if tpr > 0 {
+            // one band of (1 << lth) source rows at a time, so reads stay within a
+            // few consecutive rows
+            tiles
+                .par_chunks_exact_mut(tpr)
+                .enumerate()
+                .for_each(|(tr, tile_row)| {
+                    for (tc, tile) in tile_row.iter_mut().enumerate() {
+                        let (r0, c0) = (tr << lth, tc << LTW);
+                        *tile = Tile::from_fn_clipped(h - r0, w - c0, |rit, cit| {
+                            m.values[(r0 + rit) * w + c0 + cit]
+                        });
+                    }
+                });
+        }
+
+        TMat {
+            width: w,
+            height: h,
+            tiles,
+        }
+    }
+}
+
+impl<T: Packable, const LTW: usize> From<TMat<T, LTW>> for RowMajorMatrix<T> {
+    fn from(m: TMat<T, LTW>) -> Self {
+        let lth = Tile::<T, LTW>::LTH;
+        let w = m.width;
+        let mut values = vec![T::zeroed(); m.height() * w];
+        // a zero-width matrix has no values to copy
+        if w > 0 {
+            values
+                .par_chunks_mut(w << lth)
+                .zip(m.par_row_tiles_native())
+                .for_each(|(band, tile_row)| {
+                    // the last band may be short, and the last tile narrow
+                    for (rit, row) in band.chunks_exact_mut(w).enumerate() {
+                        for (tc, seg) in row.chunks_mut(1 << LTW).enumerate() {
+                            let src = &tile_row[tc].as_slice()[(rit << LTW)..];
+                            seg.copy_from_slice(&src[..seg.len()]);
+                        }
+                    }
+                });
+        }
+        RowMajorMatrix::new(values, w)
+    }
+}
+
+impl<T: Packable, const LTW: usize> Matrix<T> for TMat<T, LTW> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        TMat::height(self)
+    }
+
+    fn get(&self, r: usize, c: usize) -> T {
+        TMat::get(self, r, c)
+    }
+
+    type Row<'a> = RowIter<'a, T, LTW>;
+
+    fn row(&self, r: usize) -> Self::Row<'_> {
+        TMat::row(self, r)
+    }
+
+    fn row_slice(&self, r: usize) -> impl Deref<Target = [T]> {
+        // a row is spread over a whole tile row, so this always copies
+        let mut row = Vec::with_capacity(self.width);
+        for seg in self.row_segments(r) {
+            row.extend_from_slice(seg);
+        }
+        row
+    }
+
+    fn horizontally_packed_row<'a, P>(
+        &'a self,
+        r: usize,
+    ) -> (
+        impl Iterator<Item = P> + Send + Sync,
+        impl Iterator<Item = T> + Send + Sync,
+    )
+    where
+        P: PackedValue<Value = T>,
+        T: Clone + 'a,
+    {
+        let lth = Tile::<T, LTW>::LTH;
+        let n_packed = self.width / P::WIDTH;
+        let packed = if P::WIDTH <= (1 << LTW) {
+            // every packed value sits inside one tile's row segment
+            Either::Left(
+                self.row_segments(r)
+                    .flat_map(|seg| seg.chunks_exact(P::WIDTH))
+                    .map(|xs| *P::from_slice(xs)),
+            )
+        } else {
+            // packed values span several whole segments, all of them full
+            assert!(P::WIDTH <= MAX_PACKED_WIDTH);
+            let tile_row = self.tile_row(r >> lth);
+            let off = (r & ((1 << lth) - 1)) << LTW;
+            let k = P::WIDTH >> LTW;
+            Either::Right((0..n_packed).map(move |i| {
+                let mut buf = [T::zeroed(); MAX_PACKED_WIDTH];
+                for (dst, t) in buf
+                    .chunks_exact_mut(1 << LTW)
+                    .zip(&tile_row[i * k..(i + 1) * k])
+                {
+                    dst.copy_from_slice(&t.as_slice()[off..][..1 << LTW]);
+                }
+                *P::from_slice(&buf[..P::WIDTH])
+            }))
+        };
+        let suffix = TMat::row(self, r).skip(n_packed * P::WIDTH);
+        (packed, suffix)
+    }
+
+    fn vertically_packed_row<P>(&self, r: usize) -> impl Iterator<Item = P>
+    where
+        P: PackedValue<Value = T>,
+    {
+        assert!(P::WIDTH <= MAX_PACKED_WIDTH);
+        let lth = Tile::<T, LTW>::LTH;
+        let h = TMat::height(self);
+        let off = (r & ((1 << lth) - 1)) << LTW;
+        if LTW == 0 && r + P::WIDTH <= h && off + P::WIDTH <= 1 << lth {
+            // a column tile holds rows r..r + P::WIDTH of its column contiguously
+            let tile_row = self.tile_row(r >> lth);
+            return Either::Left(
+                tile_row
+                    .iter()
+                    .map(move |t| *P::from_slice(&t.as_slice()[off..off + P::WIDTH])),
+            );
+        }
+        // Row r + i (wrapping around, like Plonky3) is at `off` in each tile of
+        // `tiles`. At most two tile rows unless the packing is taller than a tile.
+        let rows: [(&[Tile<T, LTW>], usize); MAX_PACKED_WIDTH] = array::from_fn(|i| {
+            let r = (r + i % P::WIDTH) % h;
+            (self.tile_row(r >> lth), (r & ((1 << lth) - 1)) << LTW)
+        });
+        Either::Right((0..self.width).map(move |c| {
+            let (tc, cit) = (c >> LTW, c & ((1 << LTW) - 1));
+            P::from_fn(|i| {
+                let (tiles, off) = rows[i];
+                tiles[tc].as_slice()[off + cit]
+            })
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_field::Field;
+    use p3_mersenne_31::Mersenne31;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+
+    fn round_trip<const LTW: usize>(h: usize, w: usize) {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let rmm = RowMajorMatrix::<Mersenne31>::rand(&mut rng, h, w);
+        let t = TMat::<_, LTW>::from(rmm.clone());
+        for _ in 0..64 {
+            let (r, c) = (rng.gen_range(0..h), rng.gen_range(0..w));
+            assert_eq!(t.get(r, c), rmm.get(r, c));
+        }
+        let back = RowMajorMatrix::from(t);
+        assert_eq!((back.width, back.values), (rmm.width, rmm.values));
+    }
+
+    #[test]
+    fn round_trip_aligned() {
+        round_trip::<0>(64, 16);
+        round_trip::<2>(64, 16);
+        round_trip::<4>(64, 16);
+    }
+
+    #[test]
+    fn round_trip_ragged() {
+        round_trip::<1>(21, 37);
+        round_trip::<3>(21, 37);
+        round_trip::<4>(1000, 37);
+    }
+
+    #[test]
+    fn round_trip_zero_width() {
+        // a zero-width `RowMajorMatrix` has no rows either
+        let t = TMat::<Mersenne31, 2>::from_fn(5, 0, |_, _| unreachable!());
+        let rmm = RowMajorMatrix::from(t);
+        assert_eq!((rmm.width(), rmm.height()), (0, 0));
+        let t = TMat::<_, 2>::from(rmm);
+        assert_eq!((t.height(), t.width), (0, 0));
+    }
+
+    fn check_matrix_impl<const LTW: usize, P: PackedValue<Value = Mersenne31>>() {
+        let mut rng = ChaChaRng::seed_from_u64(1);
+        let rmm = RowMajorMatrix::<Mersenne31>::rand(&mut rng, 37, 70);
+        let t = TMat::<_, LTW>::from(rmm.clone());
+        assert_eq!(Matrix::dimensions(&t), rmm.dimensions());
+
+        for r in 0..rmm.height() {
+            assert!(Matrix::row(&t, r).eq(rmm.row(r)));
+            assert_eq!(*t.row_slice(r), *rmm.row_slice(r));
+
+            let (packed, suffix) = t.horizontally_packed_row::<P>(r);
+            let (ref_packed, ref_suffix) = rmm.horizontally_packed_row::<P>(r);
+            assert!(packed
+                .flat_map(|p| p.as_slice().to_vec())
+                .eq(ref_packed.flat_map(|p| p.as_slice().to_vec())));
+            assert!(suffix.eq(ref_suffix));
+
+            assert!(t
+                .vertically_packed_row::<P>(r)
+                .flat_map(|p| p.as_slice().to_vec())
+                .eq(rmm
+                    .vertically_packed_row::<P>(r)
+                    .flat_map(|p| p.as_slice().to_vec())));
+        }
+    }
+
+    #[test]
+    fn matrix_impl_matches_dense() {
+        type P = <Mersenne31 as Field>::Packing;
+        check_matrix_impl::<0, P>();
+        check_matrix_impl::<1, P>();
+        check_matrix_impl::<2, P>();
+        check_matrix_impl::<3, P>();
+        check_matrix_impl::<4, P>();
+    }
+}
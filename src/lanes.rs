@@ -0,0 +1,955 @@
+//! Native vector lanes backing a 64-byte tile.
+//!
+//! A tile is one cache line: 16 `u32`s. Depending on the target that's four NEON
+//! vectors, two AVX2 vectors, one AVX-512 vector, or (anywhere else) four plain
+//! `[u32; 4]` arrays that the compiler is free to autovectorize.
+//!
+//! There are two ways in. [`Lane`] and the free functions are the backend this
+//! crate was compiled for ([`Backend::compiled`]); they're what `Tile::vecs` and
+//! `PackedM31` are made of, so they can't change at runtime. Bulk kernels instead
+//! implement [`Kernel`] and go through [`dispatch`], which runs them against the
+//! best backend the CPU has ([`Backend::detect`]) inside a `#[target_feature]`
+//! function, so a binary built without `target-cpu=native` still gets AVX2 or
+//! AVX-512 there.
+
+use std::{array, fmt, sync::OnceLock};
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::{self, uint32x4_t};
+
+    pub type Lane = uint32x4_t;
+    pub const LANE_WIDTH: usize = 4;
+    pub const BACKEND: super::Backend = super::Backend::Neon;
+
+    #[inline(always)]
+    pub fn splat(x: u32) -> Lane {
+        unsafe { aarch64::vdupq_n_u32(x) }
+    }
+    #[inline(always)]
+    pub fn add(a: Lane, b: Lane) -> Lane {
+        unsafe { aarch64::vaddq_u32(a, b) }
+    }
+    #[inline(always)]
+    pub fn sub(a: Lane, b: Lane) -> Lane {
+        unsafe { aarch64::vsubq_u32(a, b) }
+    }
+    #[inline(always)]
+    pub fn xor(a: Lane, b: Lane) -> Lane {
+        unsafe { aarch64::veorq_u32(a, b) }
+    }
+    #[inline(always)]
+    pub fn min(a: Lane, b: Lane) -> Lane {
+        unsafe { aarch64::vminq_u32(a, b) }
+    }
+    /// canonical inputs, canonical output
+    #[inline(always)]
+    pub fn m31_mul(a: Lane, b: Lane) -> Lane {
+        unsafe {
+            let p = aarch64::vdupq_n_u32(super::M31_P);
+            // hi31 = (2ab) >> 32 = ab >> 31, can't saturate for inputs < 2^31
+            let prod_hi31 = aarch64::vreinterpretq_u32_s32(aarch64::vqdmulhq_s32(
+                aarch64::vreinterpretq_s32_u32(a),
+                aarch64::vreinterpretq_s32_u32(b),
+            ));
+            let prod_lo32 = aarch64::vmulq_u32(a, b);
+            // t = lo32 - hi31 * P = lo31 + hi31, in [0, 2P]
+            let t = aarch64::vmlsq_u32(prod_lo32, prod_hi31, p);
+            aarch64::vminq_u32(t, aarch64::vsubq_u32(t, p))
+        }
+    }
+    /// products of the low and high halves
+    pub type Wide = (aarch64::uint64x2_t, aarch64::uint64x2_t);
+
+    /// `acc + fold(a * b)`, see [`super::Simd::mul_fold_add`]
+    #[inline(always)]
+    pub fn mul_fold_add(acc: Wide, a: Lane, b: Lane) -> Wide {
+        unsafe {
+            let p = aarch64::vdupq_n_u64(super::M31_P as u64);
+            let lo = aarch64::vmull_u32(aarch64::vget_low_u32(a), aarch64::vget_low_u32(b));
+            let hi = aarch64::vmull_high_u32(a, b);
+            // lo31 + hi31 of each product
+            let lo = aarch64::vaddq_u64(aarch64::vandq_u64(lo, p), aarch64::vshrq_n_u64::<31>(lo));
+            let hi = aarch64::vaddq_u64(aarch64::vandq_u64(hi, p), aarch64::vshrq_n_u64::<31>(hi));
+            (aarch64::vaddq_u64(acc.0, lo), aarch64::vaddq_u64(acc.1, hi))
+        }
+    }
+
+    /// rows of a 4x4 tile of 32-bit elements in, columns out
+    #[inline(always)]
+    pub fn transpose_4x4(t: [Lane; 4]) -> [Lane; 4] {
+        unsafe {
+            // [a0 b0 a2 b2], [a1 b1 a3 b3] and the same for c, d
+            let ab = aarch64::vtrnq_u32(t[0], t[1]);
+            let cd = aarch64::vtrnq_u32(t[2], t[3]);
+            [
+                aarch64::vcombine_u32(aarch64::vget_low_u32(ab.0), aarch64::vget_low_u32(cd.0)),
+                aarch64::vcombine_u32(aarch64::vget_low_u32(ab.1), aarch64::vget_low_u32(cd.1)),
+                aarch64::vcombine_u32(aarch64::vget_high_u32(ab.0), aarch64::vget_high_u32(cd.0)),
+                aarch64::vcombine_u32(aarch64::vget_high_u32(ab.1), aarch64::vget_high_u32(cd.1)),
+            ]
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx512 {
+    use std::arch::x86_64::{self, __m512i};
+
+    pub type Lane = __m512i;
+    pub const LANE_WIDTH: usize = 16;
+    pub const BACKEND: super::Backend = super::Backend::Avx512;
+
+    #[inline(always)]
+    pub fn splat(x: u32) -> Lane {
+        unsafe { x86_64::_mm512_set1_epi32(x as i32) }
+    }
+    #[inline(always)]
+    pub fn add(a: Lane, b: Lane) -> Lane {
+        unsafe { x86_64::_mm512_add_epi32(a, b) }
+    }
+    #[inline(always)]
+    pub fn sub(a: Lane, b: Lane) -> Lane {
+        unsafe { x86_64::_mm512_sub_epi32(a, b) }
+    }
+    #[inline(always)]
+    pub fn xor(a: Lane, b: Lane) -> Lane {
+        unsafe { x86_64::_mm512_xor_si512(a, b) }
+    }
+    #[inline(always)]
+    pub fn min(a: Lane, b: Lane) -> Lane {
+        unsafe { x86_64::_mm512_min_epu32(a, b) }
+    }
+    /// canonical inputs, canonical output
+    #[inline(always)]
+    pub fn m31_mul(a: Lane, b: Lane) -> Lane {
+        unsafe {
+            let p = x86_64::_mm512_set1_epi32(super::M31_P as i32);
+            // full 64-bit products of the even and odd lanes
+            let evn = x86_64::_mm512_mul_epu32(a, b);
+            let odd = x86_64::_mm512_mul_epu32(
+                x86_64::_mm512_srli_epi64::<32>(a),
+                x86_64::_mm512_srli_epi64::<32>(b),
+            );
+            // low 31 and high 31 bits of each product, back in its own lane
+            let lo = x86_64::_mm512_and_si512(
+                x86_64::_mm512_mask_blend_epi32(0xaaaa, evn, x86_64::_mm512_slli_epi64::<32>(odd)),
+                p,
+            );
+            let hi = x86_64::_mm512_mask_blend_epi32(
+                0xaaaa,
+                x86_64::_mm512_srli_epi64::<31>(evn),
+                x86_64::_mm512_slli_epi64::<1>(odd),
+            );
+            let t = x86_64::_mm512_add_epi32(lo, hi);
+            x86_64::_mm512_min_epu32(t, x86_64::_mm512_sub_epi32(t, p))
+        }
+    }
+    /// products of the even and odd lanes
+    pub type Wide = (Lane, Lane);
+
+    /// lo31 + hi31 of each 64-bit lane
+    #[inline(always)]
+    fn fold(x: Lane) -> Lane {
+        unsafe {
+            let p = x86_64::_mm512_set1_epi64(super::M31_P as i64);
+            x86_64::_mm512_add_epi64(
+                x86_64::_mm512_and_si512(x, p),
+                x86_64::_mm512_srli_epi64::<31>(x),
+            )
+        }
+    }
+
+    /// `acc + fold(a * b)`, see [`super::Simd::mul_fold_add`]
+    #[inline(always)]
+    pub fn mul_fold_add(acc: Wide, a: Lane, b: Lane) -> Wide {
+        unsafe {
+            let evn = x86_64::_mm512_mul_epu32(a, b);
+            let odd = x86_64::_mm512_mul_epu32(
+                x86_64::_mm512_srli_epi64::<32>(a),
+                x86_64::_mm512_srli_epi64::<32>(b),
+            );
+            (
+                x86_64::_mm512_add_epi64(acc.0, fold(evn)),
+                x86_64::_mm512_add_epi64(acc.1, fold(odd)),
+            )
+        }
+    }
+
+    /// rows of a 4x4 tile of 32-bit elements in, columns out
+    #[inline(always)]
+    pub fn transpose_4x4(t: [Lane; 1]) -> [Lane; 1] {
+        unsafe {
+            let idx =
+                x86_64::_mm512_setr_epi32(0, 4, 8, 12, 1, 5, 9, 13, 2, 6, 10, 14, 3, 7, 11, 15);
+            [x86_64::_mm512_permutexvar_epi32(idx, t[0])]
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::{self, __m256i};
+
+    pub type Lane = __m256i;
+    pub const LANE_WIDTH: usize = 8;
+    pub const BACKEND: super::Backend = super::Backend::Avx2;
+
+    #[inline(always)]
+    pub fn splat(x: u32) -> Lane {
+        unsafe { x86_64::_mm256_set1_epi32(x as i32) }
+    }
+    #[inline(always)]
+    pub fn add(a: Lane, b: Lane) -> Lane {
+        unsafe { x86_64::_mm256_add_epi32(a, b) }
+    }
+    #[inline(always)]
+    pub fn sub(a: Lane, b: Lane) -> Lane {
+        unsafe { x86_64::_mm256_sub_epi32(a, b) }
+    }
+    #[inline(always)]
+    pub fn xor(a: Lane, b: Lane) -> Lane {
+        unsafe { x86_64::_mm256_xor_si256(a, b) }
+    }
+    #[inline(always)]
+    pub fn min(a: Lane, b: Lane) -> Lane {
+        unsafe { x86_64::_mm256_min_epu32(a, b) }
+    }
+    /// canonical inputs, canonical output
+    #[inline(always)]
+    pub fn m31_mul(a: Lane, b: Lane) -> Lane {
+        unsafe {
+            let p = x86_64::_mm256_set1_epi32(super::M31_P as i32);
+            // full 64-bit products of the even and odd lanes
+            let evn = x86_64::_mm256_mul_epu32(a, b);
+            let odd = x86_64::_mm256_mul_epu32(
+                x86_64::_mm256_srli_epi64::<32>(a),
+                x86_64::_mm256_srli_epi64::<32>(b),
+            );
+            // low 31 and high 31 bits of each product, back in its own lane
+            let lo = x86_64::_mm256_and_si256(
+                x86_64::_mm256_blend_epi32::<0xaa>(evn, x86_64::_mm256_slli_epi64::<32>(odd)),
+                p,
+            );
+            let hi = x86_64::_mm256_blend_epi32::<0xaa>(
+                x86_64::_mm256_srli_epi64::<31>(evn),
+                x86_64::_mm256_slli_epi64::<1>(odd),
+            );
+            let t = x86_64::_mm256_add_epi32(lo, hi);
+            x86_64::_mm256_min_epu32(t, x86_64::_mm256_sub_epi32(t, p))
+        }
+    }
+    /// products of the even and odd lanes
+    pub type Wide = (Lane, Lane);
+
+    /// lo31 + hi31 of each 64-bit lane
+    #[inline(always)]
+    fn fold(x: Lane) -> Lane {
+        unsafe {
+            let p = x86_64::_mm256_set1_epi64x(super::M31_P as i64);
+            x86_64::_mm256_add_epi64(
+                x86_64::_mm256_and_si256(x, p),
+                x86_64::_mm256_srli_epi64::<31>(x),
+            )
+        }
+    }
+
+    /// `acc + fold(a * b)`, see [`super::Simd::mul_fold_add`]
+    #[inline(always)]
+    pub fn mul_fold_add(acc: Wide, a: Lane, b: Lane) -> Wide {
+        unsafe {
+            let evn = x86_64::_mm256_mul_epu32(a, b);
+            let odd = x86_64::_mm256_mul_epu32(
+                x86_64::_mm256_srli_epi64::<32>(a),
+                x86_64::_mm256_srli_epi64::<32>(b),
+            );
+            (
+                x86_64::_mm256_add_epi64(acc.0, fold(evn)),
+                x86_64::_mm256_add_epi64(acc.1, fold(odd)),
+            )
+        }
+    }
+
+    /// rows of a 4x4 tile of 32-bit elements in, columns out
+    #[inline(always)]
+    pub fn transpose_4x4(t: [Lane; 2]) -> [Lane; 2] {
+        unsafe {
+            // [a0 b0 a1 b1 | a2 b2 a3 b3] and [c0 d0 c1 d1 | c2 d2 c3 d3]
+            let idx = x86_64::_mm256_setr_epi32(0, 4, 1, 5, 2, 6, 3, 7);
+            let ab = x86_64::_mm256_permutevar8x32_epi32(t[0], idx);
+            let cd = x86_64::_mm256_permutevar8x32_epi32(t[1], idx);
+            // [a0 b0 c0 d0 | a2 b2 c2 d2] and [a1 b1 c1 d1 | a3 b3 c3 d3]
+            let lo = x86_64::_mm256_unpacklo_epi64(ab, cd);
+            let hi = x86_64::_mm256_unpackhi_epi64(ab, cd);
+            [
+                x86_64::_mm256_permute2x128_si256::<0x20>(lo, hi),
+                x86_64::_mm256_permute2x128_si256::<0x31>(lo, hi),
+            ]
+        }
+    }
+}
+
+mod scalar {
+    use std::array;
+
+    pub type Lane = [u32; 4];
+    pub const LANE_WIDTH: usize = 4;
+    pub const BACKEND: super::Backend = super::Backend::Scalar;
+
+    #[inline(always)]
+    pub fn splat(x: u32) -> Lane {
+        [x; 4]
+    }
+    #[inline(always)]
+    pub fn add(a: Lane, b: Lane) -> Lane {
+        array::from_fn(|i| a[i].wrapping_add(b[i]))
+    }
+    #[inline(always)]
+    pub fn sub(a: Lane, b: Lane) -> Lane {
+        array::from_fn(|i| a[i].wrapping_sub(b[i]))
+    }
+    #[inline(always)]
+    pub fn xor(a: Lane, b: Lane) -> Lane {
+        array::from_fn(|i| a[i] ^ b[i])
+    }
+    #[inline(always)]
+    pub fn min(a: Lane, b: Lane) -> Lane {
+        array::from_fn(|i| a[i].min(b[i]))
+    }
+    /// canonical inputs, canonical output
+    #[inline(always)]
+    pub fn m31_mul(a: Lane, b: Lane) -> Lane {
+        use super::M31_P as P;
+        array::from_fn(|i| {
+            let prod = a[i] as u64 * b[i] as u64;
+            let t = (prod as u32 & P) + (prod >> 31) as u32;
+            t.min(t.wrapping_sub(P))
+        })
+    }
+    pub type Wide = [u64; 4];
+
+    /// `acc + fold(a * b)`, see [`super::Simd::mul_fold_add`]
+    #[inline(always)]
+    pub fn mul_fold_add(acc: Wide, a: Lane, b: Lane) -> Wide {
+        use super::M31_P as P;
+        array::from_fn(|i| {
+            let prod = a[i] as u64 * b[i] as u64;
+            acc[i] + (prod & P as u64) + (prod >> 31)
+        })
+    }
+
+    /// rows of a 4x4 tile of 32-bit elements in, columns out
+    #[inline(always)]
+    pub fn transpose_4x4(t: [Lane; 4]) -> [Lane; 4] {
+        array::from_fn(|i| array::from_fn(|j| t[j][i]))
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+pub use neon::*;
+
+#[cfg(all(target_arch = "x86_64", target_feature = "avx512f"))]
+pub use avx512::*;
+
+#[cfg(all(
+    target_arch = "x86_64",
+    target_feature = "avx2",
+    not(target_feature = "avx512f")
+))]
+pub use avx2::*;
+
+#[cfg(not(any(
+    target_arch = "aarch64",
+    all(target_arch = "x86_64", target_feature = "avx2")
+)))]
+pub use scalar::*;
+
+/// the Mersenne-31 prime, for [`m31_mul`]
+pub const M31_P: u32 = (1 << 31) - 1;
+
+/// number of native vectors in one 64-byte tile
+pub const LANES_PER_TILE: usize = 16 / LANE_WIDTH;
+
+const _: () = {
+    assert!(std::mem::size_of::<[Lane; LANES_PER_TILE]>() == 64);
+};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Neon,
+    Avx512,
+    Avx2,
+    Scalar,
+}
+
+impl Backend {
+    /// the backend this crate was compiled with
+    pub const fn compiled() -> Self {
+        BACKEND
+    }
+
+    /// The best backend the running CPU supports, which is what [`dispatch`] uses.
+    /// It may be better than [`Backend::compiled`] if the crate wasn't built with
+    /// `target-cpu=native`. Only checked once.
+    pub fn detect() -> Self {
+        static DETECTED: OnceLock<Backend> = OnceLock::new();
+        *DETECTED.get_or_init(|| {
+            [Backend::Neon, Backend::Avx512, Backend::Avx2]
+                .into_iter()
+                .find(|b| b.is_supported())
+                .unwrap_or(Backend::Scalar)
+        })
+    }
+
+    /// whether the running CPU can use this backend
+    pub fn is_supported(self) -> bool {
+        match self {
+            #[cfg(target_arch = "aarch64")]
+            Backend::Neon => true,
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx512 => is_x86_feature_detected!("avx512f"),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => is_x86_feature_detected!("avx2"),
+            Backend::Scalar => true,
+            #[allow(unreachable_patterns)]
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Backend::Neon => "neon",
+            Backend::Avx512 => "avx512",
+            Backend::Avx2 => "avx2",
+            Backend::Scalar => "scalar",
+        };
+        f.write_str(s)
+    }
+}
+
+/// One backend's vector ops, for code run through [`dispatch`]. The only way to
+/// get a value of an implementing type is to be handed one by `dispatch`, which
+/// has already checked the CPU, so the methods take `self` as proof.
+///
+/// Kernels must be `#[inline(always)]` all the way down: anything that isn't
+/// inlined into the `#[target_feature]` function `dispatch` calls is compiled
+/// for the baseline target.
+pub trait Simd: Copy + Send + Sync + 'static {
+    const BACKEND: Backend;
+    /// `u32`s per vector
+    const WIDTH: usize;
+    /// `WIDTH` `u32`s
+    type V: Copy;
+    /// `WIDTH` `u64` sums, in whatever arrangement suits the backend
+    type W: Copy;
+
+    /// the first `WIDTH` elements of `xs`
+    fn load(self, xs: &[u32]) -> Self::V;
+    /// into the first `WIDTH` elements of `out`
+    fn store(self, v: Self::V, out: &mut [u32]);
+    fn splat(self, x: u32) -> Self::V;
+    /// `xs[..n]` over and over, for `n` a power of two; just a load if `n >= WIDTH`
+    fn load_repeated(self, xs: &[u32], n: usize) -> Self::V;
+    /// lane `i` is lane `idx[i]` of `v`, for indices below `WIDTH`
+    fn permute(self, v: Self::V, idx: Self::V) -> Self::V;
+    fn add(self, a: Self::V, b: Self::V) -> Self::V;
+    fn sub(self, a: Self::V, b: Self::V) -> Self::V;
+    fn min(self, a: Self::V, b: Self::V) -> Self::V;
+    /// canonical inputs, canonical output
+    fn m31_mul(self, a: Self::V, b: Self::V) -> Self::V;
+
+    fn zero_wide(self) -> Self::W;
+    /// `acc + fold(a * b)` element by element, where `fold(p) = (p & P) + (p >> 31)`
+    /// takes the full 64-bit product to below 2^32 without reducing it, so `2^32`
+    /// of them fit in a sum
+    fn mul_fold_add(self, acc: Self::W, a: Self::V, b: Self::V) -> Self::W;
+    /// element `i` of `w` into `out[i]`, for `i < WIDTH`
+    fn store_wide(self, w: Self::W, out: &mut [u64]);
+}
+
+/// the even elements and then the odd ones, back in order
+#[cfg(target_arch = "x86_64")]
+#[inline(always)]
+fn interleave(evn: &[u64], odd: &[u64], out: &mut [u64]) {
+    for (out, (e, o)) in out.chunks_exact_mut(2).zip(evn.iter().zip(odd)) {
+        out[0] = *e;
+        out[1] = *o;
+    }
+}
+
+/// Code to run against whichever backend [`dispatch`] picks.
+pub trait Kernel {
+    type Output;
+    /// should be `#[inline(always)]`, see [`Simd`]
+    fn run<S: Simd>(self, s: S) -> Self::Output;
+}
+
+/// Runs `k` on the best backend the CPU supports, see [`Backend::detect`].
+#[inline]
+pub fn dispatch<K: Kernel>(k: K) -> K::Output {
+    dispatch_to(Backend::detect(), k)
+}
+
+/// Runs `k` on a particular backend, which the CPU has to support.
+pub fn dispatch_to<K: Kernel>(backend: Backend, k: K) -> K::Output {
+    assert!(backend.is_supported(), "this CPU can't run {backend}");
+    match backend {
+        #[cfg(target_arch = "aarch64")]
+        Backend::Neon => k.run(Neon(())),
+        // both checked above
+        #[cfg(target_arch = "x86_64")]
+        Backend::Avx512 => unsafe { run_avx512(k) },
+        #[cfg(target_arch = "x86_64")]
+        Backend::Avx2 => unsafe { run_avx2(k) },
+        _ => k.run(Scalar(())),
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f")]
+unsafe fn run_avx512<K: Kernel>(k: K) -> K::Output {
+    k.run(Avx512(()))
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn run_avx2<K: Kernel>(k: K) -> K::Output {
+    k.run(Avx2(()))
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Scalar(());
+
+impl Simd for Scalar {
+    const BACKEND: Backend = Backend::Scalar;
+    const WIDTH: usize = scalar::LANE_WIDTH;
+    type V = scalar::Lane;
+    type W = scalar::Wide;
+
+    #[inline(always)]
+    fn load(self, xs: &[u32]) -> Self::V {
+        xs[..Self::WIDTH].try_into().unwrap()
+    }
+    #[inline(always)]
+    fn store(self, v: Self::V, out: &mut [u32]) {
+        out[..Self::WIDTH].copy_from_slice(&v);
+    }
+    #[inline(always)]
+    fn splat(self, x: u32) -> Self::V {
+        scalar::splat(x)
+    }
+    #[inline(always)]
+    fn load_repeated(self, xs: &[u32], n: usize) -> Self::V {
+        let xs = &xs[..n.min(Self::WIDTH)];
+        array::from_fn(|i| xs[i % xs.len()])
+    }
+    #[inline(always)]
+    fn permute(self, v: Self::V, idx: Self::V) -> Self::V {
+        idx.map(|i| v[i as usize])
+    }
+    #[inline(always)]
+    fn add(self, a: Self::V, b: Self::V) -> Self::V {
+        scalar::add(a, b)
+    }
+    #[inline(always)]
+    fn sub(self, a: Self::V, b: Self::V) -> Self::V {
+        scalar::sub(a, b)
+    }
+    #[inline(always)]
+    fn min(self, a: Self::V, b: Self::V) -> Self::V {
+        scalar::min(a, b)
+    }
+    #[inline(always)]
+    fn m31_mul(self, a: Self::V, b: Self::V) -> Self::V {
+        scalar::m31_mul(a, b)
+    }
+
+    #[inline(always)]
+    fn zero_wide(self) -> Self::W {
+        [0; 4]
+    }
+    #[inline(always)]
+    fn mul_fold_add(self, acc: Self::W, a: Self::V, b: Self::V) -> Self::W {
+        scalar::mul_fold_add(acc, a, b)
+    }
+    #[inline(always)]
+    fn store_wide(self, w: Self::W, out: &mut [u64]) {
+        out[..Self::WIDTH].copy_from_slice(&w);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[derive(Copy, Clone, Debug)]
+pub struct Neon(());
+
+#[cfg(target_arch = "aarch64")]
+impl Simd for Neon {
+    const BACKEND: Backend = Backend::Neon;
+    const WIDTH: usize = neon::LANE_WIDTH;
+    type V = neon::Lane;
+    type W = neon::Wide;
+
+    #[inline(always)]
+    fn load(self, xs: &[u32]) -> Self::V {
+        let xs = &xs[..Self::WIDTH];
+        unsafe { std::arch::aarch64::vld1q_u32(xs.as_ptr()) }
+    }
+    #[inline(always)]
+    fn store(self, v: Self::V, out: &mut [u32]) {
+        let out = &mut out[..Self::WIDTH];
+        unsafe { std::arch::aarch64::vst1q_u32(out.as_mut_ptr(), v) }
+    }
+    #[inline(always)]
+    fn splat(self, x: u32) -> Self::V {
+        neon::splat(x)
+    }
+    #[inline(always)]
+    fn load_repeated(self, xs: &[u32], n: usize) -> Self::V {
+        use std::arch::aarch64;
+        match n {
+            1 => neon::splat(xs[0]),
+            2 => unsafe {
+                let pair = xs[0] as u64 | (xs[1] as u64) << 32;
+                aarch64::vreinterpretq_u32_u64(aarch64::vdupq_n_u64(pair))
+            },
+            _ => self.load(xs),
+        }
+    }
+    #[inline(always)]
+    fn permute(self, v: Self::V, idx: Self::V) -> Self::V {
+        use std::arch::aarch64;
+        unsafe {
+            // lane index i picks bytes 4i..4i + 4
+            let bytes = aarch64::vmlaq_n_u32(aarch64::vdupq_n_u32(0x0302_0100), idx, 0x0404_0404);
+            aarch64::vreinterpretq_u32_u8(aarch64::vqtbl1q_u8(
+                aarch64::vreinterpretq_u8_u32(v),
+                aarch64::vreinterpretq_u8_u32(bytes),
+            ))
+        }
+    }
+    #[inline(always)]
+    fn add(self, a: Self::V, b: Self::V) -> Self::V {
+        neon::add(a, b)
+    }
+    #[inline(always)]
+    fn sub(self, a: Self::V, b: Self::V) -> Self::V {
+        neon::sub(a, b)
+    }
+    #[inline(always)]
+    fn min(self, a: Self::V, b: Self::V) -> Self::V {
+        neon::min(a, b)
+    }
+    #[inline(always)]
+    fn m31_mul(self, a: Self::V, b: Self::V) -> Self::V {
+        neon::m31_mul(a, b)
+    }
+
+    #[inline(always)]
+    fn zero_wide(self) -> Self::W {
+        unsafe {
+            let z = std::arch::aarch64::vdupq_n_u64(0);
+            (z, z)
+        }
+    }
+    #[inline(always)]
+    fn mul_fold_add(self, acc: Self::W, a: Self::V, b: Self::V) -> Self::W {
+        neon::mul_fold_add(acc, a, b)
+    }
+    #[inline(always)]
+    fn store_wide(self, w: Self::W, out: &mut [u64]) {
+        let out = &mut out[..Self::WIDTH];
+        unsafe {
+            std::arch::aarch64::vst1q_u64(out.as_mut_ptr(), w.0);
+            std::arch::aarch64::vst1q_u64(out[2..].as_mut_ptr(), w.1);
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[derive(Copy, Clone, Debug)]
+pub struct Avx2(());
+
+#[cfg(target_arch = "x86_64")]
+impl Simd for Avx2 {
+    const BACKEND: Backend = Backend::Avx2;
+    const WIDTH: usize = avx2::LANE_WIDTH;
+    type V = avx2::Lane;
+    type W = avx2::Wide;
+
+    #[inline(always)]
+    fn load(self, xs: &[u32]) -> Self::V {
+        let xs = &xs[..Self::WIDTH];
+        unsafe { std::arch::x86_64::_mm256_loadu_si256(xs.as_ptr() as *const _) }
+    }
+    #[inline(always)]
+    fn store(self, v: Self::V, out: &mut [u32]) {
+        let out = &mut out[..Self::WIDTH];
+        unsafe { std::arch::x86_64::_mm256_storeu_si256(out.as_mut_ptr() as *mut _, v) }
+    }
+    #[inline(always)]
+    fn splat(self, x: u32) -> Self::V {
+        avx2::splat(x)
+    }
+    #[inline(always)]
+    fn load_repeated(self, xs: &[u32], n: usize) -> Self::V {
+        use std::arch::x86_64;
+        match n {
+            1 => avx2::splat(xs[0]),
+            2 => unsafe {
+                x86_64::_mm256_set1_epi64x((xs[0] as u64 | (xs[1] as u64) << 32) as i64)
+            },
+            4 => unsafe {
+                let xs = &xs[..4];
+                x86_64::_mm256_broadcastsi128_si256(
+                    x86_64::_mm_loadu_si128(xs.as_ptr() as *const _),
+                )
+            },
+            _ => self.load(xs),
+        }
+    }
+    #[inline(always)]
+    fn permute(self, v: Self::V, idx: Self::V) -> Self::V {
+        unsafe { std::arch::x86_64::_mm256_permutevar8x32_epi32(v, idx) }
+    }
+    #[inline(always)]
+    fn add(self, a: Self::V, b: Self::V) -> Self::V {
+        avx2::add(a, b)
+    }
+    #[inline(always)]
+    fn sub(self, a: Self::V, b: Self::V) -> Self::V {
+        avx2::sub(a, b)
+    }
+    #[inline(always)]
+    fn min(self, a: Self::V, b: Self::V) -> Self::V {
+        avx2::min(a, b)
+    }
+    #[inline(always)]
+    fn m31_mul(self, a: Self::V, b: Self::V) -> Self::V {
+        avx2::m31_mul(a, b)
+    }
+
+    #[inline(always)]
+    fn zero_wide(self) -> Self::W {
+        unsafe {
+            let z = std::arch::x86_64::_mm256_setzero_si256();
+            (z, z)
+        }
+    }
+    #[inline(always)]
+    fn mul_fold_add(self, acc: Self::W, a: Self::V, b: Self::V) -> Self::W {
+        avx2::mul_fold_add(acc, a, b)
+    }
+    #[inline(always)]
+    fn store_wide(self, w: Self::W, out: &mut [u64]) {
+        let mut halves = [[0u64; 4]; 2];
+        unsafe {
+            std::arch::x86_64::_mm256_storeu_si256(halves[0].as_mut_ptr() as *mut _, w.0);
+            std::arch::x86_64::_mm256_storeu_si256(halves[1].as_mut_ptr() as *mut _, w.1);
+        }
+        interleave(&halves[0], &halves[1], &mut out[..Self::WIDTH]);
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[derive(Copy, Clone, Debug)]
+pub struct Avx512(());
+
+#[cfg(target_arch = "x86_64")]
+impl Simd for Avx512 {
+    const BACKEND: Backend = Backend::Avx512;
+    const WIDTH: usize = avx512::LANE_WIDTH;
+    type V = avx512::Lane;
+    type W = avx512::Wide;
+
+    #[inline(always)]
+    fn load(self, xs: &[u32]) -> Self::V {
+        let xs = &xs[..Self::WIDTH];
+        unsafe { std::arch::x86_64::_mm512_loadu_si512(xs.as_ptr() as *const _) }
+    }
+    #[inline(always)]
+    fn store(self, v: Self::V, out: &mut [u32]) {
+        let out = &mut out[..Self::WIDTH];
+        unsafe { std::arch::x86_64::_mm512_storeu_si512(out.as_mut_ptr() as *mut _, v) }
+    }
+    #[inline(always)]
+    fn splat(self, x: u32) -> Self::V {
+        avx512::splat(x)
+    }
+    #[inline(always)]
+    fn load_repeated(self, xs: &[u32], n: usize) -> Self::V {
+        use std::arch::x86_64;
+        match n {
+            1 => avx512::splat(xs[0]),
+            2 => unsafe { x86_64::_mm512_set1_epi64((xs[0] as u64 | (xs[1] as u64) << 32) as i64) },
+            4 => unsafe {
+                let xs = &xs[..4];
+                x86_64::_mm512_broadcast_i32x4(x86_64::_mm_loadu_si128(xs.as_ptr() as *const _))
+            },
+            8 => unsafe {
+                let xs = &xs[..8];
+                x86_64::_mm512_broadcast_i64x4(x86_64::_mm256_loadu_si256(xs.as_ptr() as *const _))
+            },
+            _ => self.load(xs),
+        }
+    }
+    #[inline(always)]
+    fn permute(self, v: Self::V, idx: Self::V) -> Self::V {
+        unsafe { std::arch::x86_64::_mm512_permutexvar_epi32(idx, v) }
+    }
+    #[inline(always)]
+    fn add(self, a: Self::V, b: Self::V) -> Self::V {
+        avx512::add(a, b)
+    }
+    #[inline(always)]
+    fn sub(self, a: Self::V, b: Self::V) -> Self::V {
+        avx512::sub(a, b)
+    }
+    #[inline(always)]
+    fn min(self, a: Self::V, b: Self::V) -> Self::V {
+        avx512::min(a, b)
+    }
+    #[inline(always)]
+    fn m31_mul(self, a: Self::V, b: Self::V) -> Self::V {
+        avx512::m31_mul(a, b)
+    }
+
+    #[inline(always)]
+    fn zero_wide(self) -> Self::W {
+        unsafe {
+            let z = std::arch::x86_64::_mm512_setzero_si512();
+            (z, z)
+        }
+    }
+    #[inline(always)]
+    fn mul_fold_add(self, acc: Self::W, a: Self::V, b: Self::V) -> Self::W {
+        avx512::mul_fold_add(acc, a, b)
+    }
+    #[inline(always)]
+    fn store_wide(self, w: Self::W, out: &mut [u64]) {
+        let mut halves = [[0u64; 8]; 2];
+        unsafe {
+            std::arch::x86_64::_mm512_storeu_si512(halves[0].as_mut_ptr() as *mut _, w.0);
+            std::arch::x86_64::_mm512_storeu_si512(halves[1].as_mut_ptr() as *mut _, w.1);
+        }
+        interleave(&halves[0], &halves[1], &mut out[..Self::WIDTH]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+
+    /// `a + b`, `a - b`, `min(a, b)` and `a * b mod P`, element by element, and
+    /// `fold(a * b) + fold(a * b)` through the wide sums
+    struct Ops<'a>(&'a [u32], &'a [u32]);
+
+    impl Kernel for Ops<'_> {
+        type Output = ([Vec<u32>; 4], Vec<u64>);
+        #[inline(always)]
+        fn run<S: Simd>(self, s: S) -> Self::Output {
+            let mut out: [Vec<u32>; 4] = Default::default();
+            for o in &mut out {
+                o.resize(self.0.len(), 0);
+            }
+            let mut wide = vec![0; self.0.len()];
+            for (i, (a, b)) in self
+                .0
+                .chunks_exact(S::WIDTH)
+                .zip(self.1.chunks_exact(S::WIDTH))
+                .enumerate()
+            {
+                let (a, b) = (s.load(a), s.load(b));
+                let res = [s.add(a, b), s.sub(a, b), s.min(a, b), s.m31_mul(a, b)];
+                for (o, v) in out.iter_mut().zip(res) {
+                    s.store(v, &mut o[i * S::WIDTH..]);
+                }
+                let w = s.mul_fold_add(s.zero_wide(), a, b);
+                s.store_wide(s.mul_fold_add(w, a, b), &mut wide[i * S::WIDTH..]);
+            }
+            (out, wide)
+        }
+    }
+
+    #[test]
+    fn backends_match_scalar() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let edges = [0, 1, M31_P - 1, 1 << 30];
+        let mut gen = |i: usize| match i % 3 {
+            0 => edges[i / 3 % edges.len()],
+            _ => rng.gen_range(0..M31_P),
+        };
+        let a: Vec<u32> = (0..1024).map(&mut gen).collect();
+        let b: Vec<u32> = (0..1024).rev().map(&mut gen).collect();
+
+        let backends = [
+            Backend::Neon,
+            Backend::Avx512,
+            Backend::Avx2,
+            Backend::Scalar,
+        ];
+        for backend in backends.into_iter().filter(|b| b.is_supported()) {
+            let ([add, sub, min, mul], wide) = dispatch_to(backend, Ops(&a, &b));
+            for (i, (&x, &y)) in a.iter().zip(&b).enumerate() {
+                assert_eq!(add[i], x.wrapping_add(y), "{backend}: {x} + {y}");
+                assert_eq!(sub[i], x.wrapping_sub(y), "{backend}: {x} - {y}");
+                assert_eq!(min[i], x.min(y), "{backend}: min({x}, {y})");
+                let prod = (x as u64 * y as u64 % M31_P as u64) as u32;
+                assert_eq!(mul[i], prod, "{backend}: {x} * {y}");
+                let p = x as u64 * y as u64;
+                let folded = (p & M31_P as u64) + (p >> 31);
+                assert_eq!(wide[i], 2 * folded, "{backend}: wide {x} * {y}");
+            }
+        }
+        assert!(Backend::detect().is_supported());
+    }
+
+    /// `load_repeated` for every `n` up to a tile, and a `permute` that both
+    /// reverses and duplicates lanes
+    struct Shuffles<'a>(&'a [u32]);
+
+    impl Kernel for Shuffles<'_> {
+        type Output = (Vec<Vec<u32>>, Vec<u32>);
+        #[inline(always)]
+        fn run<S: Simd>(self, s: S) -> Self::Output {
+            let repeated = [1, 2, 4, 8, 16]
+                .map(|n| {
+                    let mut out = vec![0; S::WIDTH];
+                    s.store(s.load_repeated(self.0, n), &mut out);
+                    out
+                })
+                .to_vec();
+            let idx: Vec<u32> = (0..S::WIDTH as u32)
+                .map(|i| (S::WIDTH as u32 - 1 - i) / 2)
+                .collect();
+            let mut permuted = vec![0; S::WIDTH];
+            s.store(s.permute(s.load(self.0), s.load(&idx)), &mut permuted);
+            (repeated, permuted)
+        }
+    }
+
+    #[test]
+    fn shuffles_match_scalar() {
+        let xs: Vec<u32> = (100..116).collect();
+        let backends = [
+            Backend::Neon,
+            Backend::Avx512,
+            Backend::Avx2,
+            Backend::Scalar,
+        ];
+        for backend in backends.into_iter().filter(|b| b.is_supported()) {
+            let (repeated, permuted) = dispatch_to(backend, Shuffles(&xs));
+            let width = permuted.len();
+            for (n, out) in [1, 2, 4, 8, 16].into_iter().zip(&repeated) {
+                let expected = (0..width).map(|i| xs[i % n]);
+                assert!(out.iter().copied().eq(expected), "{backend}: n = {n}");
+            }
+            let expected = (0..width).map(|i| xs[(width - 1 - i) / 2]);
+            assert!(permuted.iter().copied().eq(expected), "{backend}: permute");
+        }
+    }
+}
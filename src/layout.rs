@@ -0,0 +1,477 @@
+//! One interface over every storage layout, so an algorithm can be written once
+//! and benchmarked on all of them.
+//!
+//! Each layout splits itself into row bands and column bands, and each band into
+//! [`Block`]s: whatever piece the layout keeps contiguous. For the tiled layouts
+//! that's a tile, for `RmMat` a row segment, for `CmMat` a column segment. Folding
+//! over blocks rather than elements lets generic code keep the layout's locality.
+
+use std::{cmp, ops::Range};
+
+use rayon::prelude::*;
+
+use crate::{
+    col_major::CmMat,
+    packable::Packable,
+    row_major::{RmMat, CHUNK},
+    tile_map::{MappedMat, TileMap},
+    tiled_mat::{TMat, Tile},
+    tiles,
+    tinym31::M31,
+};
+
+/// A strided view of the `height` x `width` piece of the matrix starting at
+/// `(r0, c0)`. Padding is never included.
+#[derive(Copy, Clone, Debug)]
+pub struct Block<'a, T> {
+    pub r0: usize,
+    pub c0: usize,
+    pub height: usize,
+    pub width: usize,
+    data: &'a [T],
+    row_stride: usize,
+    col_stride: usize,
+}
+
+impl<'a, T: Packable> Block<'a, T> {
+    fn new(r0: usize, c0: usize, height: usize, width: usize, data: &'a [T]) -> Self {
+        // row-major with no gaps unless the caller says otherwise
+        Self::strided(r0, c0, height, width, data, width, 1)
+    }
+
+    fn strided(
+        r0: usize,
+        c0: usize,
+        height: usize,
+        width: usize,
+        data: &'a [T],
+        row_stride: usize,
+        col_stride: usize,
+    ) -> Self {
+        Self {
+            r0,
+            c0,
+            height,
+            width,
+            data,
+            row_stride,
+            col_stride,
+        }
+    }
+
+    fn tile<const LTW: usize>(
+        tr: usize,
+        tc: usize,
+        tile: &'a Tile<T, LTW>,
+        h: usize,
+        w: usize,
+    ) -> Self {
+        let lth = Tile::<T, LTW>::LTH;
+        let (r0, c0) = (tr << lth, tc << LTW);
+        let (bh, bw) = (cmp::min(1 << lth, h - r0), cmp::min(1 << LTW, w - c0));
+        Self::strided(r0, c0, bh, bw, tile.as_slice(), 1 << LTW, 1)
+    }
+
+    pub fn rows(&self) -> Range<usize> {
+        self.r0..self.r0 + self.height
+    }
+
+    pub fn cols(&self) -> Range<usize> {
+        self.c0..self.c0 + self.width
+    }
+
+    /// `i` and `j` are relative to the block
+    pub fn get(&self, i: usize, j: usize) -> T {
+        debug_assert!(i < self.height && j < self.width);
+        self.data[i * self.row_stride + j * self.col_stride]
+    }
+
+    /// row `i` (relative to the block) as one slice, if the block is stored row
+    /// by row
+    pub fn row_slice(&self, i: usize) -> Option<&'a [T]> {
+        (self.col_stride == 1).then(|| &self.data[i * self.row_stride..][..self.width])
+    }
+
+    /// column `j` (relative to the block) as one slice, if the block is stored
+    /// column by column
+    pub fn col_slice(&self, j: usize) -> Option<&'a [T]> {
+        (self.row_stride == 1).then(|| &self.data[j * self.col_stride..][..self.height])
+    }
+
+    /// `(r, c, value)` in row-major order, with absolute coordinates
+    pub fn iter(&self) -> impl Iterator<Item = (usize, usize, T)> + '_ {
+        (0..self.height).flat_map(move |i| {
+            (0..self.width).map(move |j| (self.r0 + i, self.c0 + j, self.get(i, j)))
+        })
+    }
+}
+
+pub trait MatrixLayout<T: Packable>: Sync {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn get(&self, r: usize, c: usize) -> T;
+
+    /// rows per row band; the last band may be short
+    fn row_band_height(&self) -> usize;
+    /// columns per column band; the last band may be narrow
+    fn col_band_width(&self) -> usize;
+
+    /// the blocks covering row band `band`, left to right
+    fn row_band_blocks(&self, band: usize) -> impl Iterator<Item = Block<'_, T>> + Send;
+    /// the blocks covering column band `band`, top to bottom
+    fn col_band_blocks(&self, band: usize) -> impl Iterator<Item = Block<'_, T>> + Send;
+
+    fn dimensions(&self) -> (usize, usize) {
+        (self.height(), self.width())
+    }
+
+    fn row_band(&self, band: usize) -> Range<usize> {
+        let bh = self.row_band_height();
+        band * bh..cmp::min((band + 1) * bh, self.height())
+    }
+
+    fn col_band(&self, band: usize) -> Range<usize> {
+        let bw = self.col_band_width();
+        band * bw..cmp::min((band + 1) * bw, self.width())
+    }
+
+    /// the row range of every row band
+    fn par_row_bands(&self) -> impl IndexedParallelIterator<Item = Range<usize>> + '_ {
+        (0..self.height().div_ceil(self.row_band_height()))
+            .into_par_iter()
+            .map(|band| self.row_band(band))
+    }
+
+    /// the column range of every column band
+    fn par_col_bands(&self) -> impl IndexedParallelIterator<Item = Range<usize>> + '_ {
+        (0..self.width().div_ceil(self.col_band_width()))
+            .into_par_iter()
+            .map(|band| self.col_band(band))
+    }
+
+    /// One accumulator per row band, fed its blocks left to right. `init` gets the
+    /// band's row range.
+    fn fold_row_bands<Acc, Init, Op>(&self, init: Init, op: Op) -> Vec<Acc>
+    where
+        Acc: Send + Sync,
+        Init: Fn(Range<usize>) -> Acc + Send + Sync,
+        Op: Fn(Acc, Block<'_, T>) -> Acc + Send + Sync,
+    {
+        self.par_row_bands()
+            .enumerate()
+            .map(|(band, rows)| self.row_band_blocks(band).fold(init(rows), &op))
+            .collect()
+    }
+
+    /// One accumulator per column band, fed its blocks top to bottom. `init` gets
+    /// the band's column range.
+    fn fold_col_bands<Acc, Init, Op>(&self, init: Init, op: Op) -> Vec<Acc>
+    where
+        Acc: Send + Sync,
+        Init: Fn(Range<usize>) -> Acc + Send + Sync,
+        Op: Fn(Acc, Block<'_, T>) -> Acc + Send + Sync,
+    {
+        self.par_col_bands()
+            .enumerate()
+            .map(|(band, cols)| self.col_band_blocks(band).fold(init(cols), &op))
+            .collect()
+    }
+}
+
+/// `sum_r v[r] * row_r` for any layout, written once against `MatrixLayout`. Each
+/// task folds its row bands into a sum per column, reading every block through
+/// whichever slices it's stored in.
+pub fn colwise_dot_product<L: MatrixLayout<M31>>(m: &L, v: &[M31]) -> Vec<M31> {
+    let (h, w) = m.dimensions();
+    assert_eq!(v.len(), h, "need one entry per row of a {h}x{w} matrix");
+    let zero = || vec![M31::ZERO; w];
+    m.par_row_bands()
+        .enumerate()
+        .fold(zero, |mut acc, (band, _)| {
+            for block in m.row_band_blocks(band) {
+                let vs = &v[block.rows()];
+                let acc = &mut acc[block.cols()];
+                if block.row_slice(0).is_some() {
+                    for (i, y) in vs.iter().enumerate() {
+                        let row = block.row_slice(i).unwrap();
+                        for (a, x) in acc.iter_mut().zip(row) {
+                            *a += *x * *y;
+                        }
+                    }
+                } else {
+                    for (j, a) in acc.iter_mut().enumerate() {
+                        let col = block.col_slice(j).unwrap();
+                        *a += col.iter().zip(vs).map(|(x, y)| *x * *y).sum();
+                    }
+                }
+            }
+            acc
+        })
+        .reduce(zero, |mut l, r| {
+            for (a, b) in l.iter_mut().zip(r) {
+                *a += b;
+            }
+            l
+        })
+}
+
+impl<T: Packable, const LTW: usize> MatrixLayout<T> for TMat<T, LTW> {
+    fn width(&self) -> usize {
+        self.width
+    }
+    fn height(&self) -> usize {
+        self.height
+    }
+    fn get(&self, r: usize, c: usize) -> T {
+        TMat::get(self, r, c)
+    }
+
+    fn row_band_height(&self) -> usize {
+        1 << Tile::<T, LTW>::LTH
+    }
+    fn col_band_width(&self) -> usize {
+        1 << LTW
+    }
+
+    fn row_band_blocks(&self, band: usize) -> impl Iterator<Item = Block<'_, T>> + Send {
+        let (h, w) = (self.height, self.width);
+        self.tile_row(band)
+            .iter()
+            .enumerate()
+            .map(move |(tc, tile)| Block::tile(band, tc, tile, h, w))
+    }
+    fn col_band_blocks(&self, band: usize) -> impl Iterator<Item = Block<'_, T>> + Send {
+        let (h, w) = (self.height, self.width);
+        // a zero-height matrix has columns but no tiles
+        self.tiles[cmp::min(band, self.tiles.len())..]
+            .iter()
+            .step_by(self.tiles_per_row())
+            .enumerate()
+            .map(move |(tr, tile)| Block::tile(tr, band, tile, h, w))
+    }
+}
+
+impl<T: Packable, const LTW: usize> MatrixLayout<T> for tiles::Mat<T, LTW> {
+    fn width(&self) -> usize {
+        tiles::Mat::width(self)
+    }
+    fn height(&self) -> usize {
+        tiles::Mat::height(self)
+    }
+    fn get(&self, r: usize, c: usize) -> T {
+        self.load_scalar(r, c)
+    }
+
+    fn row_band_height(&self) -> usize {
+        1 << tiles::Tile::<T, LTW>::LTH
+    }
+    fn col_band_width(&self) -> usize {
+        1 << LTW
+    }
+
+    // always whole tiles, so no clipping
+    fn row_band_blocks(&self, band: usize) -> impl Iterator<Item = Block<'_, T>> + Send {
+        let (th, tw) = (self.row_band_height(), self.col_band_width());
+        let tpr = tiles::Mat::width(self) >> LTW;
+        self.tiles()[band * tpr..(band + 1) * tpr]
+            .iter()
+            .enumerate()
+            .map(move |(tc, t)| Block::new(band * th, tc * tw, th, tw, t.as_slice()))
+    }
+    fn col_band_blocks(&self, band: usize) -> impl Iterator<Item = Block<'_, T>> + Send {
+        let (th, tw) = (self.row_band_height(), self.col_band_width());
+        let tpr = tiles::Mat::width(self) >> LTW;
+        self.tiles()[cmp::min(band, self.tiles().len())..]
+            .iter()
+            .step_by(tpr)
+            .enumerate()
+            .map(move |(tr, t)| Block::new(tr * th, band * tw, th, tw, t.as_slice()))
+    }
+}
+
+impl<T: Packable, const LTW: usize, M: TileMap> MatrixLayout<T> for MappedMat<T, LTW, M> {
+    fn width(&self) -> usize {
+        self.width
+    }
+    fn height(&self) -> usize {
+        self.height
+    }
+    fn get(&self, r: usize, c: usize) -> T {
+        MappedMat::get(self, r, c)
+    }
+
+    fn row_band_height(&self) -> usize {
+        1 << Tile::<T, LTW>::LTH
+    }
+    fn col_band_width(&self) -> usize {
+        1 << LTW
+    }
+
+    fn row_band_blocks(&self, band: usize) -> impl Iterator<Item = Block<'_, T>> + Send {
+        let (h, w) = (self.height, self.width);
+        (0..self.tiles_per_row()).map(move |tc| Block::tile(band, tc, self.tile(band, tc), h, w))
+    }
+    fn col_band_blocks(&self, band: usize) -> impl Iterator<Item = Block<'_, T>> + Send {
+        let (h, w) = (self.height, self.width);
+        (0..self.tile_rows()).map(move |tr| Block::tile(tr, band, self.tile(tr, band), h, w))
+    }
+}
+
+impl MatrixLayout<M31> for RmMat {
+    fn width(&self) -> usize {
+        RmMat::width(self)
+    }
+    fn height(&self) -> usize {
+        RmMat::height(self)
+    }
+    fn get(&self, r: usize, c: usize) -> M31 {
+        RmMat::get(self, r, c)
+    }
+
+    fn row_band_height(&self) -> usize {
+        1
+    }
+    fn col_band_width(&self) -> usize {
+        CHUNK
+    }
+
+    fn row_band_blocks(&self, band: usize) -> impl Iterator<Item = Block<'_, M31>> + Send {
+        self.row(band)
+            .chunks(CHUNK)
+            .enumerate()
+            .map(move |(i, seg)| Block::new(band, i * CHUNK, 1, seg.len(), seg))
+    }
+    fn col_band_blocks(&self, band: usize) -> impl Iterator<Item = Block<'_, M31>> + Send {
+        let cols = self.col_band(band);
+        (0..RmMat::height(self))
+            .map(move |r| Block::new(r, cols.start, 1, cols.len(), &self.row(r)[cols.clone()]))
+    }
+}
+
+impl MatrixLayout<M31> for CmMat {
+    fn width(&self) -> usize {
+        CmMat::width(self)
+    }
+    fn height(&self) -> usize {
+        CmMat::height(self)
+    }
+    fn get(&self, r: usize, c: usize) -> M31 {
+        CmMat::get(self, r, c)
+    }
+
+    fn row_band_height(&self) -> usize {
+        CHUNK
+    }
+    fn col_band_width(&self) -> usize {
+        1
+    }
+
+    // column segments are contiguous down the rows, so the row stride is 1
+    fn row_band_blocks(&self, band: usize) -> impl Iterator<Item = Block<'_, M31>> + Send {
+        let rows = self.row_band(band);
+        (0..CmMat::width(self)).map(move |c| {
+            let seg = &self.col(c)[rows.clone()];
+            Block::strided(rows.start, c, seg.len(), 1, seg, 1, 0)
+        })
+    }
+    fn col_band_blocks(&self, band: usize) -> impl Iterator<Item = Block<'_, M31>> + Send {
+        self.col(band)
+            .chunks(CHUNK)
+            .enumerate()
+            .map(move |(i, seg)| Block::strided(i * CHUNK, band, seg.len(), 1, seg, 1, 0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+    use crate::{
+        blocked::{BlockedMat, PageMat},
+        curve::{HilbertMat, MortonMat},
+    };
+
+    /// sums every row and column through the block folds, then checks
+    /// `colwise_dot_product`
+    fn check_layout(m: &impl MatrixLayout<M31>, naive: &RmMat) {
+        let (h, w) = naive.dimensions();
+        assert_eq!(m.dimensions(), (h, w));
+        if h > 0 && w > 0 {
+            for (r, c) in [(0, 0), (h - 1, w - 1), (h / 2, w / 3)] {
+                assert_eq!(m.get(r, c), naive.get(r, c));
+            }
+        }
+
+        let mut row_sums = vec![M31::ZERO; h];
+        let mut seen = 0;
+        for (rows, sums) in m.fold_row_bands(
+            |rows| (rows.clone(), vec![M31::ZERO; rows.len()]),
+            |(rows, mut acc), block| {
+                assert!(block.rows().start >= rows.start && block.rows().end <= rows.end);
+                for (r, _, x) in block.iter() {
+                    acc[r - rows.start] += x;
+                }
+                (rows, acc)
+            },
+        ) {
+            assert_eq!(rows.start, seen);
+            seen = rows.end;
+            row_sums[rows].copy_from_slice(&sums);
+        }
+        assert_eq!(seen, h);
+        let expected: Vec<M31> = (0..h).map(|r| naive.row(r).iter().copied().sum()).collect();
+        assert_eq!(row_sums, expected);
+
+        let col_sums: Vec<M31> = m
+            .fold_col_bands(
+                |cols| (cols.clone(), vec![M31::ZERO; cols.len()]),
+                |(cols, mut acc), block| {
+                    for (_, c, x) in block.iter() {
+                        acc[c - cols.start] += x;
+                    }
+                    (cols, acc)
+                },
+            )
+            .into_iter()
+            .flat_map(|(_, acc)| acc)
+            .collect();
+        let expected: Vec<M31> = (0..w)
+            .map(|c| (0..h).map(|r| naive.get(r, c)).sum())
+            .collect();
+        assert_eq!(col_sums, expected);
+
+        let v: Vec<M31> = (0..h).map(|r| M31::from_canonical(r as u32 + 1)).collect();
+        let expected: Vec<M31> = (0..w)
+            .map(|c| (0..h).map(|r| v[r] * naive.get(r, c)).sum())
+            .collect();
+        assert_eq!(colwise_dot_product(m, &v), expected);
+    }
+
+    #[test]
+    fn all_layouts_agree() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        // whole tiles for `tiles::Mat`, ragged for the rest
+        for (h, w) in [(64, 64), (37, 70), (0, 64), (37, 0)] {
+            let naive = RmMat::from_fn(h, w, |_, _| rng.gen());
+            let f = |r, c| naive.get(r, c);
+            check_layout(&naive, &naive);
+            check_layout(&CmMat::from(&naive), &naive);
+            check_layout(&TMat::<M31, 0>::from_fn(h, w, f), &naive);
+            check_layout(&TMat::<M31, 2>::from_fn(h, w, f), &naive);
+            check_layout(&TMat::<M31, 4>::from_fn(h, w, f), &naive);
+            check_layout(&MortonMat::<M31, 2>::from_fn(h, w, f), &naive);
+            check_layout(&HilbertMat::<M31, 3>::from_fn(h, w, f), &naive);
+            check_layout(&PageMat::<M31, 2>::from_fn(h, w, f), &naive);
+            check_layout(
+                &BlockedMat::<M31, 4, 2, 1, true, true>::from_fn(h, w, f),
+                &naive,
+            );
+            if h % 16 == 0 && w % 16 == 0 {
+                check_layout(&tiles::Mat::<M31, 1>::from_fn(h, w, f), &naive);
+                check_layout(&tiles::Mat::<M31, 4>::from_fn(h, w, f), &naive);
+            }
+        }
+    }
+}
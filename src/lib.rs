@@ -18,9 +18,22 @@ use p3_mersenne_31::Mersenne31;
 use p3_util::reverse_slice_index_bits;
 use rand::Rng;
 
-mod tinym31;
-
+pub mod tinym31;
+
+pub mod blocked;
+pub mod cfft;
+pub mod col_major;
+pub mod curve;
+mod interop;
+pub mod lanes;
+pub mod layout;
+pub mod linalg;
+pub mod packable;
+pub mod packed_m31;
+pub mod row_major;
+pub mod tile_map;
 pub mod tiled_mat;
+pub mod tiles;
 
 type F = Mersenne31;
 
@@ -124,6 +137,28 @@ fn interp_simple(xs: Vec<F>, twiddles: Vec<Vec<F>>) -> Vec<F> {
     xs
 }
 
+/// The inverse of `interp_simple`: the same layers backwards with `dit` and the
+/// twiddles themselves, which also undoes the `1/n`, then re-interleave.
+fn eval_simple(mut xs: Vec<F>, twiddles: Vec<Vec<F>>) -> Vec<F> {
+    for mut ts in twiddles.into_iter().rev() {
+        reverse_slice_index_bits(&mut ts);
+
+        for blk in xs.chunks_exact_mut(ts.len() * 2) {
+            let (los, his) = blk.split_at_mut(ts.len());
+            for (&t, lo, hi) in izip!(&ts, los, his) {
+                (*lo, *hi) = dit(t, *lo, *hi);
+            }
+        }
+    }
+
+    // re-interleave
+    let (lo, hi) = xs.split_at(xs.len() / 2);
+    lo.iter()
+        .copied()
+        .interleave(hi.iter().rev().copied())
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -168,4 +203,15 @@ mod tests {
             assert_eq!(evals[i], eval2);
         }
     }
+
+    #[test]
+    fn eval_inverts_interp() {
+        let mut rng = ChaChaRng::seed_from_u64(1);
+        for log_n in 1..=20 {
+            let twiddles = compute_twiddles(CircleDomain::standard(log_n));
+            let evals: Vec<F> = (0..1 << log_n).map(|_| rng.gen()).collect();
+            let coeffs = interp_simple(evals.clone(), twiddles.clone());
+            assert_eq!(eval_simple(coeffs, twiddles), evals, "log_n = {log_n}");
+        }
+    }
 }
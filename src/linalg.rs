@@ -0,0 +1,845 @@
+//! Products and dot products over M31 tiles, with delayed reduction: each product
+//! is only folded to below 2^32 (`lo31 + hi31`, which is congruent mod P), sums of
+//! those are kept in `u64` lanes, and the full reduction happens once at the end.
+//! That's good for 2^32 terms per sum.
+//!
+//! The tile kernels go through [`lanes::dispatch`], so the widening multiplies run
+//! on the best vector unit the CPU has. Each task dispatches once for a band of
+//! tiles and keeps its sums in wide vectors until the band is done.
+
+use std::{array, cmp};
+
+use p3_field::{
+    extension::{BinomialExtensionField, Complex},
+    AbstractExtensionField, AbstractField, Field, PrimeField32,
+};
+use p3_mersenne_31::Mersenne31;
+use rayon::prelude::*;
+
+use crate::{
+    lanes::{self, Kernel, Simd},
+    tiled_mat::{TMat, Tile},
+    tinym31::M31,
+};
+
+/// M31 elements per tile
+const TILE_LEN: usize = 16;
+
+/// Unreduced per-element sums for one tile position.
+type TileSums = [u64; TILE_LEN];
+
+/// `p` folded to below 2^32, not reduced
+#[inline]
+fn fold(p: u64) -> u64 {
+    (p & M31::P as u64) + (p >> 31)
+}
+
+fn add_sums(mut l: Vec<TileSums>, r: Vec<TileSums>) -> Vec<TileSums> {
+    for (l, r) in l.iter_mut().zip(&r) {
+        for (a, b) in l.iter_mut().zip(r) {
+            *a += b;
+        }
+    }
+    l
+}
+
+/// A tile's worth of `u32`s to multiply a tile by, element by element.
+type Bcast = [u32; TILE_LEN];
+
+/// the sum of row `rit` of a tile's sums
+fn row_total<const LTW: usize>(sums: &TileSums, rit: usize) -> u64 {
+    sums[rit << LTW..][..1 << LTW].iter().sum()
+}
+
+/// the sum of column `cit` of a tile's sums
+fn col_total<const LTW: usize>(sums: &TileSums, cit: usize) -> u64 {
+    let lth = Tile::<M31, LTW>::LTH;
+    (0..1 << lth).map(|rit| sums[(rit << LTW) + cit]).sum()
+}
+
+/// One tile per tile column with `v[c0..c0 + 2^LTW]` in every row, to multiply
+/// against the matrix's tiles for `M * v`. Past the end of `v` it's zeros.
+fn row_broadcast_tiles<const LTW: usize>(v: &[M31]) -> Vec<Bcast> {
+    v.chunks(1 << LTW)
+        .map(|vs| array::from_fn(|i| vs.get(i & ((1 << LTW) - 1)).map_or(0, |x| x.value())))
+        .collect()
+}
+
+/// One tile per tile row with `v[r0..r0 + 2^LTH]` in every column, for `v^T * M`.
+fn col_broadcast_tiles<const LTW: usize>(v: &[M31]) -> Vec<Bcast> {
+    v.chunks(1 << Tile::<M31, LTW>::LTH)
+        .map(|vs| array::from_fn(|i| vs.get(i >> LTW).map_or(0, |x| x.value())))
+        .collect()
+}
+
+/// Tile rows (or columns) per task in `columnwise_dot_product`, `dot_ext_powers`,
+/// `mat_vec` and `vec_mat`.
+const BAND: usize = 32;
+
+/// `M * v` or `v^T * M` over a `rows x cols` rectangle of tiles: each tile is
+/// multiplied by its broadcast tile of `v` and added into the sums for its tile
+/// row (`mat_vec`) or tile column (`vec_mat`).
+struct TileProduct<'a, const LTW: usize> {
+    /// starting at the rectangle's top-left tile, `stride` tiles per row
+    tiles: &'a [Tile<M31, LTW>],
+    stride: usize,
+    rows: usize,
+    cols: usize,
+    /// one per tile column of the rectangle for `mat_vec`, per tile row for `vec_mat`
+    v_tiles: &'a [Bcast],
+    /// one per tile row or column of the rectangle, added to at the end
+    sums: &'a mut [TileSums],
+    vec_mat: bool,
+}
+
+impl<const LTW: usize> Kernel for TileProduct<'_, LTW> {
+    type Output = ();
+
+    #[inline(always)]
+    fn run<S: Simd>(self, s: S) {
+        let n = TILE_LEN / S::WIDTH;
+        let mut acc = vec![s.zero_wide(); self.sums.len() * n];
+        for r in 0..self.rows {
+            let row = &self.tiles[r * self.stride..][..self.cols];
+            for (c, tile) in row.iter().enumerate() {
+                let (d, y) = if self.vec_mat {
+                    (c, &self.v_tiles[r])
+                } else {
+                    (r, &self.v_tiles[c])
+                };
+                let x = tile.values();
+                for (j, a) in acc[d * n..][..n].iter_mut().enumerate() {
+                    let (x, y) = (s.load(&x[j * S::WIDTH..]), s.load(&y[j * S::WIDTH..]));
+                    *a = s.mul_fold_add(*a, x, y);
+                }
+            }
+        }
+        let mut buf = [0; TILE_LEN];
+        for (sums, acc) in self.sums.iter_mut().zip(acc.chunks_exact(n)) {
+            for (j, a) in acc.iter().enumerate() {
+                s.store_wide(*a, &mut buf[j * S::WIDTH..]);
+            }
+            for (sum, b) in sums.iter_mut().zip(&buf) {
+                *sum += b;
+            }
+        }
+    }
+}
+
+/// Most coordinates an `M31Extension` can have, and most vectors a tile can take
+/// (4-lane backends), so `dot_ext_powers` can keep its sums on the stack.
+const MAX_EXT_DEGREE: usize = 4;
+const MAX_VECS_PER_TILE: usize = TILE_LEN / 4;
+
+/// `dot_ext_powers` over a band of whole tile rows. Each tile row's sums stay in
+/// wide vectors, one set per coordinate, until the row is done.
+struct DotPowers<'a, const LTW: usize, EF> {
+    tiles: &'a [Tile<M31, LTW>],
+    tpr: usize,
+    /// coordinate `k` of tile column `tc` at `tc * EF::DEGREE + k`
+    power_tiles: &'a [Bcast],
+    out: &'a mut [EF],
+}
+
+impl<const LTW: usize, EF: M31Extension> Kernel for DotPowers<'_, LTW, EF> {
+    type Output = ();
+
+    #[inline(always)]
+    fn run<S: Simd>(self, s: S) {
+        let (d, n) = (EF::DEGREE, TILE_LEN / S::WIDTH);
+        let lth = Tile::<M31, LTW>::LTH;
+        let tile_rows = self.tiles.chunks_exact(self.tpr);
+        for (out, tile_row) in self.out.chunks_mut(1 << lth).zip(tile_rows) {
+            let mut acc = [[s.zero_wide(); MAX_VECS_PER_TILE]; MAX_EXT_DEGREE];
+            for (tile, pows) in tile_row.iter().zip(self.power_tiles.chunks_exact(d)) {
+                let x = tile.values();
+                for j in 0..n {
+                    let x = s.load(&x[j * S::WIDTH..]);
+                    for (acc, pow) in acc.iter_mut().zip(pows) {
+                        acc[j] = s.mul_fold_add(acc[j], x, s.load(&pow[j * S::WIDTH..]));
+                    }
+                }
+            }
+            let mut sums = [[0; TILE_LEN]; MAX_EXT_DEGREE];
+            for (sums, acc) in sums.iter_mut().zip(&acc).take(d) {
+                for (j, a) in acc[..n].iter().enumerate() {
+                    s.store_wide(*a, &mut sums[j * S::WIDTH..]);
+                }
+            }
+            for (rit, o) in out.iter_mut().enumerate() {
+                *o = EF::from_coords(|k| M31::from_wrapped_u64(row_total::<LTW>(&sums[k], rit)));
+            }
+        }
+    }
+}
+
+/// How `mat_vec` and `vec_mat` split up the matrix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parallelism {
+    /// a task per `BAND` tile rows; `vec_mat` adds up the tasks' partial results
+    RowBands,
+    /// a task per `BAND` tile columns; `mat_vec` adds up the tasks' partial results
+    ColBands,
+}
+
+/// Output rows per `matmul` task. A task's tile rows of the left-hand side are
+/// reused for every block of output tile columns, and a block's tile columns of
+/// the right-hand side for every tile row of the task.
+const MM_ROWS: usize = 32;
+
+/// The register-blocked core of `matmul`: `NR` output tiles side by side, summed
+/// over the whole inner dimension straight from the tiles of both sides. Entry `p`
+/// spreads column `p` of the left-hand tile row so each element fills its row of
+/// an output tile, and repeats row `p` of each right-hand tile down it. On the 4x4
+/// and 2x8 tiles that's one `permute` of the loaded left-hand tile and a
+/// `load_repeated` per output tile on AVX-512; vectors that stay within a tile
+/// row (AVX2 on 2x8, NEON on both) take splats and plain loads instead.
+///
+/// Each output tile's sums take 2 vectors on AVX-512, 4 on AVX2 and 8 on NEON,
+/// so `NR` is picked per backend to keep them in registers.
+#[inline(always)]
+fn mm_kernel<S: Simd, const LTW: usize, const NR: usize>(
+    s: S,
+    a_row: &[Tile<M31, LTW>],
+    b: &[Tile<M31, LTW>],
+    b_tpr: usize,
+    inner: usize,
+) -> [[S::W; MAX_VECS_PER_TILE]; NR] {
+    let (w, lth) = (1 << LTW, Tile::<M31, LTW>::LTH);
+    let n = TILE_LEN / S::WIDTH;
+    // lane i of column k spread over a vector: lane (i & !(w - 1)) + k of the tile's
+    let spread: [u32; TILE_LEN] = array::from_fn(|i| (i & !(w - 1)) as u32);
+    let spread = s.load(&spread);
+    let mut acc = [[s.zero_wide(); MAX_VECS_PER_TILE]; NR];
+    for (kt, a) in a_row.iter().enumerate() {
+        let x = a.values();
+        let mut xs = [s.splat(0); MAX_VECS_PER_TILE];
+        if S::WIDTH > w {
+            for (j, v) in xs[..n].iter_mut().enumerate() {
+                *v = s.load(&x[j * S::WIDTH..]);
+            }
+        }
+        for k in 0..cmp::min(w, inner - (kt << LTW)) {
+            let mut col = [s.splat(0); MAX_VECS_PER_TILE];
+            for (j, v) in col[..n].iter_mut().enumerate() {
+                *v = if S::WIDTH > w {
+                    s.permute(xs[j], s.add(spread, s.splat(k as u32)))
+                } else {
+                    s.splat(x[((j * S::WIDTH) & !(w - 1)) + k])
+                };
+            }
+            let p = (kt << LTW) + k;
+            let (tr, rit) = (p >> lth, p & ((1 << lth) - 1));
+            for (acc, b) in acc.iter_mut().zip(&b[tr * b_tpr..][..NR]) {
+                let row = &b.values()[rit << LTW..][..w];
+                for (j, a) in acc[..n].iter_mut().enumerate() {
+                    let y = if S::WIDTH > w {
+                        s.load_repeated(row, w)
+                    } else {
+                        s.load(&row[(j * S::WIDTH) & (w - 1)..])
+                    };
+                    *a = s.mul_fold_add(*a, col[j], y);
+                }
+            }
+        }
+    }
+    acc
+}
+
+/// `matmul` for one band of at most `MM_ROWS` output rows, written straight into
+/// the band's output tiles.
+struct MatmulBand<'a, const LTW: usize> {
+    /// the band's tile rows of the left-hand side
+    a: &'a [Tile<M31, LTW>],
+    b: &'a [Tile<M31, LTW>],
+    inner: usize,
+    rows: usize,
+    width: usize,
+    out: &'a mut [Tile<M31, LTW>],
+}
+
+impl<const LTW: usize> Kernel for MatmulBand<'_, LTW> {
+    type Output = ();
+
+    #[inline(always)]
+    fn run<S: Simd>(self, s: S) {
+        if S::WIDTH == TILE_LEN {
+            self.run_tiles::<S, 4>(s)
+        } else {
+            self.run_tiles::<S, 2>(s)
+        }
+    }
+}
+
+impl<const LTW: usize> MatmulBand<'_, LTW> {
+    #[inline(always)]
+    fn run_tiles<S: Simd, const NR: usize>(mut self, s: S) {
+        let tpr = self.width.div_ceil(1 << LTW);
+        let a_tpr = self.inner.div_ceil(1 << LTW);
+        for tc0 in (0..tpr).step_by(NR) {
+            let b = &self.b[tc0..];
+            for (tr, a_row) in self.a.chunks_exact(a_tpr).enumerate() {
+                // the last block may be narrower
+                match cmp::min(NR, tpr - tc0) {
+                    1 => self.write(
+                        s,
+                        &mm_kernel::<S, LTW, 1>(s, a_row, b, tpr, self.inner),
+                        tr,
+                        tc0,
+                    ),
+                    2 => self.write(
+                        s,
+                        &mm_kernel::<S, LTW, 2>(s, a_row, b, tpr, self.inner),
+                        tr,
+                        tc0,
+                    ),
+                    3 => self.write(
+                        s,
+                        &mm_kernel::<S, LTW, 3>(s, a_row, b, tpr, self.inner),
+                        tr,
+                        tc0,
+                    ),
+                    _ => self.write(
+                        s,
+                        &mm_kernel::<S, LTW, NR>(s, a_row, b, tpr, self.inner),
+                        tr,
+                        tc0,
+                    ),
+                }
+            }
+        }
+    }
+
+    /// reduces `acc` into the output tiles from `(tr, tc0)` on
+    #[inline(always)]
+    fn write<S: Simd>(&mut self, s: S, acc: &[[S::W; MAX_VECS_PER_TILE]], tr: usize, tc0: usize) {
+        let lth = Tile::<M31, LTW>::LTH;
+        let tpr = self.width.div_ceil(1 << LTW);
+        let n = TILE_LEN / S::WIDTH;
+        let r0 = tr << lth;
+        for (tc, acc) in (tc0..).zip(acc) {
+            let mut sums = [0; TILE_LEN];
+            for (j, a) in acc[..n].iter().enumerate() {
+                s.store_wide(*a, &mut sums[j * S::WIDTH..]);
+            }
+            let c0 = tc << LTW;
+            self.out[tr * tpr + tc] =
+                Tile::from_fn_clipped(self.rows - r0, self.width - c0, |rit, cit| {
+                    M31::from_wrapped_u64(sums[(rit << LTW) + cit])
+                });
+        }
+    }
+}
+
+/// An extension of M31 that `dot_ext_powers` can take apart into `DEGREE` M31
+/// coordinates and put back together.
+pub trait M31Extension: Field {
+    const DEGREE: usize;
+    fn coord(&self, k: usize) -> M31;
+    fn from_coords(f: impl FnMut(usize) -> M31) -> Self;
+}
+
+impl M31Extension for Mersenne31 {
+    const DEGREE: usize = 1;
+    fn coord(&self, _k: usize) -> M31 {
+        M31::from_canonical(self.as_canonical_u32())
+    }
+    fn from_coords(mut f: impl FnMut(usize) -> M31) -> Self {
+        Mersenne31::from_canonical_u32(f(0).value())
+    }
+}
+
+impl M31Extension for Complex<Mersenne31> {
+    const DEGREE: usize = 2;
+    fn coord(&self, k: usize) -> M31 {
+        AbstractExtensionField::<Mersenne31>::as_base_slice(self)[k].coord(0)
+    }
+    fn from_coords(mut f: impl FnMut(usize) -> M31) -> Self {
+        AbstractExtensionField::<Mersenne31>::from_base_fn(|i| Mersenne31::from_coords(|_| f(i)))
+    }
+}
+
+/// the degree-4 field, as a quadratic extension of the complex one
+impl M31Extension for BinomialExtensionField<Complex<Mersenne31>, 2> {
+    const DEGREE: usize = 4;
+    fn coord(&self, k: usize) -> M31 {
+        AbstractExtensionField::<Complex<Mersenne31>>::as_base_slice(self)[k >> 1].coord(k & 1)
+    }
+    fn from_coords(mut f: impl FnMut(usize) -> M31) -> Self {
+        AbstractExtensionField::<Complex<Mersenne31>>::from_base_fn(|i| {
+            Complex::from_coords(|j| f(2 * i + j))
+        })
+    }
+}
+
+impl<const LTW: usize> TMat<M31, LTW> {
+    /// `v` has one entry per row; returns `sum_r v[r] * row_r`, one entry per
+    /// column, like Plonky3's `Matrix::columnwise_dot_product`.
+    ///
+    /// Each tile is multiplied element-wise by a tile's worth of its row's `v`
+    /// entries. A task takes `BAND` tile rows and keeps one `u64` sum per element
+    /// of a tile row; the rows inside a tile are only added together at the end.
+    pub fn columnwise_dot_product(&self, v: &[M31]) -> Vec<M31> {
+        assert_eq!(
+            v.len(),
+            self.height,
+            "need one entry per row of a {}x{} matrix",
+            self.height,
+            self.width
+        );
+        let tpr = self.tiles_per_row();
+        if tpr == 0 {
+            return vec![];
+        }
+        let v_tiles = col_broadcast_tiles::<LTW>(v);
+        let zero = || vec![[0; TILE_LEN]; tpr];
+        let sums = self
+            .tiles
+            .par_chunks(BAND * tpr)
+            .zip(v_tiles.par_chunks(BAND))
+            .fold(zero, |mut sums, (tiles, v_tiles)| {
+                lanes::dispatch(TileProduct {
+                    tiles,
+                    stride: tpr,
+                    rows: v_tiles.len(),
+                    cols: tpr,
+                    v_tiles,
+                    sums: &mut sums,
+                    vec_mat: true,
+                });
+                sums
+            })
+            .reduce(zero, add_sums);
+
+        (0..self.width)
+            .map(|c| {
+                let cit = c & ((1 << LTW) - 1);
+                M31::from_wrapped_u64(col_total::<LTW>(&sums[c >> LTW], cit))
+            })
+            .collect()
+    }
+
+    /// `M * v`: `v` has one entry per column, the result one per row.
+    ///
+    /// Every matrix tile is multiplied element-wise by a tile's worth of `v` and
+    /// added into `u64` sums. With row bands each band of tile rows is reduced as
+    /// soon as it's done; with column bands every task has sums for every tile
+    /// row, and the tasks' sums are added before the one reduction.
+    pub fn mat_vec(&self, v: &[M31], par: Parallelism) -> Vec<M31> {
+        assert_eq!(
+            v.len(),
+            self.width,
+            "need one entry per column of a {}x{} matrix",
+            self.height,
+            self.width
+        );
+        let lth = Tile::<M31, LTW>::LTH;
+        let tpr = self.tiles_per_row();
+        if tpr == 0 {
+            return vec![M31::ZERO; self.height];
+        }
+        let v_tiles = row_broadcast_tiles::<LTW>(v);
+        let row_sum = |sums: &[TileSums], r: usize| {
+            M31::from_wrapped_u64(row_total::<LTW>(&sums[r >> lth], r & ((1 << lth) - 1)))
+        };
+        match par {
+            Parallelism::RowBands => {
+                let mut out = vec![M31::ZERO; self.height];
+                out.par_chunks_mut(BAND << lth)
+                    .zip(self.tiles.par_chunks(BAND * tpr))
+                    .for_each(|(out, tiles)| {
+                        let rows = tiles.len() / tpr;
+                        let mut sums = vec![[0; TILE_LEN]; rows];
+                        lanes::dispatch(TileProduct {
+                            tiles,
+                            stride: tpr,
+                            rows,
+                            cols: tpr,
+                            v_tiles: &v_tiles,
+                            sums: &mut sums,
+                            vec_mat: false,
+                        });
+                        for (r, o) in out.iter_mut().enumerate() {
+                            *o = row_sum(&sums, r);
+                        }
+                    });
+                out
+            }
+            Parallelism::ColBands => {
+                let tile_rows = self.tiles.len() / tpr;
+                let zero = || vec![[0; TILE_LEN]; tile_rows];
+                let sums = (0..tpr.div_ceil(BAND))
+                    .into_par_iter()
+                    .fold(zero, |mut sums, band| {
+                        let tc0 = band * BAND;
+                        lanes::dispatch(TileProduct {
+                            // a zero-height matrix has columns but no tiles
+                            tiles: &self.tiles[cmp::min(tc0, self.tiles.len())..],
+                            stride: tpr,
+                            rows: tile_rows,
+                            cols: cmp::min(BAND, tpr - tc0),
+                            v_tiles: &v_tiles[tc0..],
+                            sums: &mut sums,
+                            vec_mat: false,
+                        });
+                        sums
+                    })
+                    .reduce(zero, add_sums);
+                (0..self.height).map(|r| row_sum(&sums, r)).collect()
+            }
+        }
+    }
+
+    /// `v^T * M`: `v` has one entry per row, the result one per column. The
+    /// mirror of `mat_vec`: here column bands finish their columns on their own,
+    /// and row bands, which are `columnwise_dot_product`, keep `u64` sums for
+    /// every tile column until they're added.
+    pub fn vec_mat(&self, v: &[M31], par: Parallelism) -> Vec<M31> {
+        assert_eq!(
+            v.len(),
+            self.height,
+            "need one entry per row of a {}x{} matrix",
+            self.height,
+            self.width
+        );
+        match par {
+            Parallelism::RowBands => self.columnwise_dot_product(v),
+            Parallelism::ColBands => {
+                let tpr = self.tiles_per_row();
+                if tpr == 0 {
+                    return vec![];
+                }
+                let v_tiles = col_broadcast_tiles::<LTW>(v);
+                let col_sum = |sums: &[TileSums], c: usize| {
+                    M31::from_wrapped_u64(col_total::<LTW>(&sums[c >> LTW], c & ((1 << LTW) - 1)))
+                };
+                let mut out = vec![M31::ZERO; self.width];
+                out.par_chunks_mut(BAND << LTW)
+                    .enumerate()
+                    .for_each(|(band, out)| {
+                        let tc0 = band * BAND;
+                        let cols = out.len().div_ceil(1 << LTW);
+                        let mut sums = vec![[0; TILE_LEN]; cols];
+                        lanes::dispatch(TileProduct {
+                            tiles: &self.tiles[cmp::min(tc0, self.tiles.len())..],
+                            stride: tpr,
+                            rows: v_tiles.len(),
+                            cols,
+                            v_tiles: &v_tiles,
+                            sums: &mut sums,
+                            vec_mat: true,
+                        });
+                        for (c, o) in out.iter_mut().enumerate() {
+                            *o = col_sum(&sums, c);
+                        }
+                    });
+                out
+            }
+        }
+    }
+
+    /// `self * other`, in parallel over bands of `MM_ROWS` output rows.
+    ///
+    /// A band goes through its output tiles a block of tile columns at a time,
+    /// running `mm_kernel` on each of its tile rows of `self` against the block's
+    /// tile columns of `other`, as tiled, and writes the reduced sums into its own
+    /// output tiles.
+    pub fn matmul(&self, other: &Self) -> Self {
+        assert_eq!(
+            self.width, other.height,
+            "can't multiply {}x{} by {}x{}",
+            self.height, self.width, other.height, other.width
+        );
+        let lth = Tile::<M31, LTW>::LTH;
+        let (height, width, inner) = (self.height, other.width, self.width);
+        let mut out = TMat::zero(height, width);
+        if inner == 0 || width == 0 {
+            return out;
+        }
+        let (a_tpr, c_tpr) = (self.tiles_per_row(), other.tiles_per_row());
+        let band_tile_rows = MM_ROWS >> lth;
+        out.tiles
+            .par_chunks_mut(band_tile_rows * c_tpr)
+            .zip(self.tiles.par_chunks(band_tile_rows * a_tpr))
+            .enumerate()
+            .for_each(|(band, (out, a))| {
+                lanes::dispatch(MatmulBand {
+                    a,
+                    b: &other.tiles,
+                    inner,
+                    rows: cmp::min(MM_ROWS, height - band * MM_ROWS),
+                    width,
+                    out,
+                });
+            });
+        out
+    }
+
+    /// `sum_c alpha^c * row[c]` for every row, like Plonky3's
+    /// `Matrix::dot_ext_powers` (but collected).
+    ///
+    /// The powers are packed as `u32` tiles once, a set per coordinate of `EF`
+    /// with every row of a tile holding the same powers, so each tile of the
+    /// matrix gets one widening multiply-add per coordinate.
+    pub fn dot_ext_powers<EF: M31Extension>(&self, alpha: EF) -> Vec<EF> {
+        assert!(EF::DEGREE <= MAX_EXT_DEGREE, "extension of degree > 4");
+        let lth = Tile::<M31, LTW>::LTH;
+        let (d, tpr) = (EF::DEGREE, self.tiles_per_row());
+        if tpr == 0 {
+            return vec![EF::zero(); self.height];
+        }
+        let powers: Vec<EF> = alpha.powers().take(self.width).collect();
+        let power_tiles: Vec<Bcast> = (0..tpr)
+            .flat_map(|tc| (0..d).map(move |k| (tc, k)))
+            .map(|(tc, k)| {
+                array::from_fn(|i| {
+                    let c = (tc << LTW) + (i & ((1 << LTW) - 1));
+                    powers.get(c).map_or(0, |p| p.coord(k).value())
+                })
+            })
+            .collect();
+
+        let mut out = vec![EF::zero(); self.height];
+        out.par_chunks_mut(BAND << lth)
+            .zip(self.tiles.par_chunks(BAND * tpr))
+            .for_each(|(out, tiles)| {
+                lanes::dispatch(DotPowers {
+                    tiles,
+                    tpr,
+                    power_tiles: &power_tiles,
+                    out,
+                })
+            });
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_matrix::{dense::RowMajorMatrix, Matrix};
+    use p3_mersenne_31::Mersenne31;
+    use rand::{
+        distributions::{Distribution, Standard},
+        Rng, SeedableRng,
+    };
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+
+    fn p3(x: M31) -> Mersenne31 {
+        Mersenne31::from_canonical_u32(x.value())
+    }
+
+    fn check_columnwise_dot_product<const LTW: usize>(h: usize, w: usize) {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let vals: Vec<M31> = (0..h * w).map(|_| rng.gen()).collect();
+        let v: Vec<M31> = (0..h).map(|_| rng.gen()).collect();
+
+        let m = TMat::<M31, LTW>::from_fn(h, w, |r, c| vals[r * w + c]);
+        let rm = RowMajorMatrix::new(vals.iter().copied().map(p3).collect(), w);
+        let expected = rm.columnwise_dot_product(&v.iter().copied().map(p3).collect::<Vec<_>>());
+
+        let got: Vec<Mersenne31> = m.columnwise_dot_product(&v).into_iter().map(p3).collect();
+        assert_eq!(got, expected, "LTW = {LTW}, {h}x{w}");
+    }
+
+    #[test]
+    fn columnwise_dot_product_matches_p3() {
+        for (h, w) in [(64, 64), (37, 11), (1, 70), (1000, 5), (0, 3)] {
+            check_columnwise_dot_product::<0>(h, w);
+            check_columnwise_dot_product::<2>(h, w);
+            check_columnwise_dot_product::<3>(h, w);
+            check_columnwise_dot_product::<4>(h, w);
+        }
+    }
+
+    /// ragged edges, a single row, zero height and zero width
+    const PRODUCT_SHAPES: [(usize, usize); 6] =
+        [(64, 64), (37, 11), (1, 70), (1000, 5), (0, 3), (5, 0)];
+
+    fn check_mat_vec<const LTW: usize>(h: usize, w: usize) {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let vals: Vec<M31> = (0..h * w).map(|_| rng.gen()).collect();
+        let v: Vec<M31> = (0..w).map(|_| rng.gen()).collect();
+
+        let m = TMat::<M31, LTW>::from_fn(h, w, |r, c| vals[r * w + c]);
+        // `RowMajorMatrix` can't be zero wide
+        let expected = if w == 0 {
+            vec![Mersenne31::zero(); h]
+        } else {
+            let rm = RowMajorMatrix::new(vals.iter().copied().map(p3).collect(), w);
+            let v: Vec<Mersenne31> = v.iter().copied().map(p3).collect();
+            rm.rows()
+                .map(|row| row.zip(&v).map(|(x, y)| x * *y).sum())
+                .collect()
+        };
+        for par in [Parallelism::RowBands, Parallelism::ColBands] {
+            let got: Vec<Mersenne31> = m.mat_vec(&v, par).into_iter().map(p3).collect();
+            assert_eq!(got, expected, "{par:?}, LTW = {LTW}, {h}x{w}");
+        }
+    }
+
+    fn check_vec_mat<const LTW: usize>(h: usize, w: usize) {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let vals: Vec<M31> = (0..h * w).map(|_| rng.gen()).collect();
+        let v: Vec<M31> = (0..h).map(|_| rng.gen()).collect();
+
+        let m = TMat::<M31, LTW>::from_fn(h, w, |r, c| vals[r * w + c]);
+        let expected = if w == 0 {
+            vec![]
+        } else {
+            let rm = RowMajorMatrix::new(vals.iter().copied().map(p3).collect(), w);
+            rm.columnwise_dot_product(&v.iter().copied().map(p3).collect::<Vec<_>>())
+        };
+        for par in [Parallelism::RowBands, Parallelism::ColBands] {
+            let got: Vec<Mersenne31> = m.vec_mat(&v, par).into_iter().map(p3).collect();
+            assert_eq!(got, expected, "{par:?}, LTW = {LTW}, {h}x{w}");
+        }
+    }
+
+    #[test]
+    fn mat_vec_matches_p3() {
+        for (h, w) in PRODUCT_SHAPES {
+            check_mat_vec::<0>(h, w);
+            check_mat_vec::<2>(h, w);
+            check_mat_vec::<3>(h, w);
+            check_mat_vec::<4>(h, w);
+        }
+    }
+
+    #[test]
+    fn vec_mat_matches_p3() {
+        for (h, w) in PRODUCT_SHAPES {
+            check_vec_mat::<0>(h, w);
+            check_vec_mat::<2>(h, w);
+            check_vec_mat::<3>(h, w);
+            check_vec_mat::<4>(h, w);
+        }
+    }
+
+    fn check_dot_ext_powers<EF, const LTW: usize>(h: usize, w: usize)
+    where
+        EF: M31Extension,
+        Standard: Distribution<EF>,
+    {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let vals: Vec<M31> = (0..h * w).map(|_| rng.gen::<M31>()).collect();
+        let alpha: EF = rng.gen();
+
+        let m = TMat::<M31, LTW>::from_fn(h, w, |r, c| vals[r * w + c]);
+        let expected: Vec<EF> = vals
+            .chunks_exact(w)
+            .map(|row| {
+                row.iter()
+                    .zip(alpha.powers())
+                    .map(|(x, p)| p * EF::from_coords(|k| if k == 0 { *x } else { M31::ZERO }))
+                    .sum()
+            })
+            .collect();
+        assert_eq!(m.dot_ext_powers(alpha), expected, "LTW = {LTW}, {h}x{w}");
+    }
+
+    #[test]
+    fn dot_ext_powers_matches_naive() {
+        type Quartic = BinomialExtensionField<Complex<Mersenne31>, 2>;
+        for (h, w) in [(64, 64), (37, 11), (1, 70), (70, 1)] {
+            check_dot_ext_powers::<Quartic, 0>(h, w);
+            check_dot_ext_powers::<Quartic, 2>(h, w);
+            check_dot_ext_powers::<Quartic, 4>(h, w);
+            check_dot_ext_powers::<Complex<Mersenne31>, 3>(h, w);
+            check_dot_ext_powers::<Mersenne31, 1>(h, w);
+        }
+        // no columns, so every row's sum is empty
+        let alpha: Quartic = ChaChaRng::seed_from_u64(0).gen();
+        let m = TMat::<M31, 2>::from_fn(5, 0, |_, _| unreachable!());
+        assert_eq!(m.dot_ext_powers(alpha), vec![Quartic::zero(); 5]);
+    }
+
+    #[test]
+    fn dot_ext_powers_matches_p3() {
+        let (h, w) = (37, 70);
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let vals: Vec<M31> = (0..h * w).map(|_| rng.gen()).collect();
+        let alpha: Complex<Mersenne31> = rng.gen();
+
+        let m = TMat::<M31, 2>::from_fn(h, w, |r, c| vals[r * w + c]);
+        let rm = RowMajorMatrix::new(vals.iter().copied().map(p3).collect(), w);
+        let expected: Vec<_> = rm.dot_ext_powers(alpha).collect();
+        assert_eq!(m.dot_ext_powers(alpha), expected);
+    }
+
+    fn check_matmul<const LTW: usize>(n: usize, k: usize, m: usize) {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let a: Vec<M31> = (0..n * k).map(|_| rng.gen()).collect();
+        let b: Vec<M31> = (0..k * m).map(|_| rng.gen()).collect();
+        let expected = TMat::<M31, LTW>::from_fn(n, m, |r, c| {
+            (0..k).map(|p| a[r * k + p] * b[p * m + c]).sum()
+        });
+
+        let ta = TMat::<M31, LTW>::from_fn(n, k, |r, c| a[r * k + c]);
+        let tb = TMat::<M31, LTW>::from_fn(k, m, |r, c| b[r * m + c]);
+        let c = ta.matmul(&tb);
+        assert_eq!((c.height, c.width), (n, m));
+        assert_eq!(c.tiles, expected.tiles, "LTW = {LTW}, {n}x{k} by {k}x{m}");
+    }
+
+    #[test]
+    fn matmul_matches_naive() {
+        // ragged tiles, and ragged blocks of output tile columns
+        for (n, k, m) in [
+            (64, 64, 64),
+            (37, 300, 11),
+            (1, 70, 90),
+            (90, 1, 70),
+            (5, 0, 7),
+        ] {
+            check_matmul::<0>(n, k, m);
+            check_matmul::<1>(n, k, m);
+            check_matmul::<2>(n, k, m);
+            check_matmul::<3>(n, k, m);
+            check_matmul::<4>(n, k, m);
+        }
+    }
+
+    #[test]
+    fn matmul_of_max_values() {
+        let n = 300;
+        let a = TMat::<M31, 2>::from_fn(n, n, |_, _| M31::NEG_ONE);
+        let c = a.matmul(&a);
+        let expected = M31::from_wrapped_u64(n as u64);
+        assert!((0..n).all(|r| (0..n).all(|col| c.get(r, col) == expected)));
+    }
+
+    #[test]
+    fn columnwise_dot_product_of_max_values() {
+        // every sum as large as it gets, so the u64 sums have to hold up
+        let (h, w) = (4096, 16);
+        let m = TMat::<M31, 2>::from_fn(h, w, |_, _| M31::NEG_ONE);
+        let v = vec![M31::NEG_ONE; h];
+        let expected = M31::from_wrapped_u64(h as u64);
+        assert_eq!(m.columnwise_dot_product(&v), vec![expected; w]);
+    }
+
+    #[test]
+    fn mat_vec_of_max_values() {
+        // every sum as large as it gets, so the u64 sums have to hold up
+        let (h, w) = (16, 4096);
+        let m = TMat::<M31, 2>::from_fn(h, w, |_, _| M31::NEG_ONE);
+        let v = vec![M31::NEG_ONE; w];
+        let expected = vec![M31::from_wrapped_u64(w as u64); h];
+        for par in [Parallelism::RowBands, Parallelism::ColBands] {
+            assert_eq!(m.mat_vec(&v, par), expected, "{par:?}");
+        }
+    }
+
+    #[test]
+    fn vec_mat_of_max_values() {
+        let (h, w) = (4096, 16);
+        let m = TMat::<M31, 2>::from_fn(h, w, |_, _| M31::NEG_ONE);
+        let v = vec![M31::NEG_ONE; h];
+        let expected = vec![M31::from_wrapped_u64(h as u64); w];
+        for par in [Parallelism::RowBands, Parallelism::ColBands] {
+            assert_eq!(m.vec_mat(&v, par), expected, "{par:?}");
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Element types that can live in a 64-byte tile.
+
+use std::mem;
+
+use p3_field::extension::{BinomialExtensionField, Complex};
+use p3_mersenne_31::Mersenne31;
+
+use crate::tinym31::M31;
+
+/// # Safety
+/// All-zero bytes must be a valid value (tiles start out zeroed and padding stays
+/// that way), and the size must be a power of two no bigger than a tile.
+pub unsafe trait Packable: Copy + Send + Sync + 'static {
+    /// log2 of the number of elements in one 64-byte tile
+    const LOG_PER_TILE: usize = {
+        let size = mem::size_of::<Self>();
+        assert!(size.is_power_of_two() && size <= 64);
+        6 - size.ilog2() as usize
+    };
+
+    fn zeroed() -> Self {
+        unsafe { mem::zeroed() }
+    }
+}
+
+unsafe impl Packable for u8 {}
+unsafe impl Packable for u16 {}
+unsafe impl Packable for u32 {}
+unsafe impl Packable for u64 {}
+
+unsafe impl Packable for M31 {}
+
+unsafe impl Packable for Mersenne31 {}
+unsafe impl Packable for Complex<Mersenne31> {}
+/// M31 has no quartic binomial extension of its own; this is the degree-4 field
+/// built on top of the complex extension.
+unsafe impl Packable for BinomialExtensionField<Complex<Mersenne31>, 2> {}
@@ -0,0 +1,162 @@
+//! `M31`s packed into one native lane, so tile arithmetic runs on whatever backend
+//! `lanes` was compiled with.
+
+use std::{
+    fmt, mem,
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use crate::{
+    lanes::{self, Lane, LANE_WIDTH, M31_P},
+    tinym31::M31,
+};
+
+/// `WIDTH` canonical `M31`s in one native vector.
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+pub struct PackedM31(pub Lane);
+
+const _: () = {
+    assert!(mem::size_of::<PackedM31>() == mem::size_of::<[M31; LANE_WIDTH]>());
+};
+
+impl PackedM31 {
+    pub const WIDTH: usize = LANE_WIDTH;
+
+    #[inline]
+    pub fn splat(x: M31) -> Self {
+        Self(lanes::splat(x.value()))
+    }
+
+    #[inline]
+    pub fn from_array(xs: [M31; LANE_WIDTH]) -> Self {
+        unsafe { mem::transmute(xs) }
+    }
+
+    #[inline]
+    pub fn to_array(self) -> [M31; LANE_WIDTH] {
+        unsafe { mem::transmute(self) }
+    }
+
+    pub fn from_fn(f: impl FnMut(usize) -> M31) -> Self {
+        Self::from_array(std::array::from_fn(f))
+    }
+
+    #[inline]
+    pub fn square(self) -> Self {
+        self * self
+    }
+}
+
+impl Add for PackedM31 {
+    type Output = Self;
+    #[inline]
+    fn add(self, rhs: Self) -> Self {
+        let t = lanes::add(self.0, rhs.0);
+        Self(lanes::min(t, lanes::sub(t, lanes::splat(M31_P))))
+    }
+}
+impl AddAssign for PackedM31 {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for PackedM31 {
+    type Output = Self;
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        let d = lanes::sub(self.0, rhs.0);
+        Self(lanes::min(d, lanes::add(d, lanes::splat(M31_P))))
+    }
+}
+impl SubAssign for PackedM31 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for PackedM31 {
+    type Output = Self;
+    #[inline]
+    fn neg(self) -> Self {
+        Self(lanes::splat(0)) - self
+    }
+}
+
+impl Mul for PackedM31 {
+    type Output = Self;
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        Self(lanes::m31_mul(self.0, rhs.0))
+    }
+}
+impl MulAssign for PackedM31 {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl PartialEq for PackedM31 {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_array() == other.to_array()
+    }
+}
+impl Eq for PackedM31 {}
+
+impl fmt::Debug for PackedM31 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.to_array()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+
+    #[test]
+    fn matches_scalar() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let edges = [
+            M31::ZERO,
+            M31::ONE,
+            M31::NEG_ONE,
+            M31::from_canonical(1 << 30),
+        ];
+        for i in 0..1000 {
+            // mix in the edge values so every op sees them in every lane
+            let mut gen = |j: usize| match rng.gen_range(0..4) {
+                0 => edges[(i + j) % edges.len()],
+                _ => rng.gen(),
+            };
+            let a: [M31; LANE_WIDTH] = std::array::from_fn(&mut gen);
+            let b: [M31; LANE_WIDTH] = std::array::from_fn(&mut gen);
+            let (pa, pb) = (PackedM31::from_array(a), PackedM31::from_array(b));
+
+            let add = (pa + pb).to_array();
+            let sub = (pa - pb).to_array();
+            let mul = (pa * pb).to_array();
+            let neg = (-pa).to_array();
+            let sq = pa.square().to_array();
+            for j in 0..LANE_WIDTH {
+                let (x, y) = (a[j], b[j]);
+                assert_eq!(add[j], x + y, "{x} + {y}");
+                assert_eq!(sub[j], x - y, "{x} - {y}");
+                assert_eq!(mul[j], x * y, "{x} * {y}");
+                assert_eq!(neg[j], -x);
+                assert_eq!(sq[j], x.square());
+            }
+        }
+    }
+
+    #[test]
+    fn splat_round_trip() {
+        let x = M31::from_canonical(12345);
+        assert_eq!(PackedM31::splat(x).to_array(), [x; LANE_WIDTH]);
+        let p = PackedM31::from_fn(|i| M31::from_canonical(i as u32));
+        assert_eq!(PackedM31::from_array(p.to_array()), p);
+    }
+}
@@ -1,5 +1,232 @@
+//! Plain row-major storage, the baseline every tiled layout gets compared against.
+
+use std::{cmp, fmt, ops::Range};
+
+use rayon::{iter::Either, prelude::*};
+
+use crate::tinym31::M31;
+
+/// elements per cache line, the unit `fold_rows` and `fold_cols` hand to `op`
+pub const CHUNK: usize = 16;
+
+#[derive(Clone, PartialEq, Eq)]
 pub struct RmMat {
     h: usize,
     w: usize,
     vals: Vec<M31>,
 }
+
+impl RmMat {
+    pub fn zero(height: usize, width: usize) -> Self {
+        Self {
+            h: height,
+            w: width,
+            vals: vec![M31::ZERO; height * width],
+        }
+    }
+
+    pub fn from_fn(height: usize, width: usize, mut f: impl FnMut(usize, usize) -> M31) -> Self {
+        let mut vals = Vec::with_capacity(height * width);
+        for r in 0..height {
+            vals.extend((0..width).map(|c| f(r, c)));
+        }
+        Self {
+            h: height,
+            w: width,
+            vals,
+        }
+    }
+
+    /// `vals.len()` must be `height * width`
+    pub fn new(height: usize, width: usize, vals: Vec<M31>) -> Self {
+        assert_eq!(vals.len(), height * width, "{height}x{width} matrix");
+        Self {
+            h: height,
+            w: width,
+            vals,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.w
+    }
+
+    pub fn height(&self) -> usize {
+        self.h
+    }
+
+    pub fn values(&self) -> &[M31] {
+        &self.vals
+    }
+
+    pub fn bytes(&self) -> usize {
+        std::mem::size_of_val(self.vals.as_slice())
+    }
+
+    pub fn get(&self, r: usize, c: usize) -> M31 {
+        self.vals[self.index(r, c)]
+    }
+
+    pub fn set(&mut self, r: usize, c: usize, val: M31) {
+        let i = self.index(r, c);
+        self.vals[i] = val;
+    }
+
+    fn index(&self, r: usize, c: usize) -> usize {
+        let (h, w) = (self.h, self.w);
+        assert!(
+            r < h && c < w,
+            "({r}, {c}) out of bounds for {h}x{w} matrix"
+        );
+        r * w + c
+    }
+
+    pub fn row(&self, r: usize) -> &[M31] {
+        let h = self.h;
+        assert!(r < h, "row {r} out of bounds for a matrix of height {h}");
+        &self.vals[r * self.w..][..self.w]
+    }
+
+    pub fn par_rows(&self) -> impl IndexedParallelIterator<Item = &[M31]> {
+        (0..self.h).into_par_iter().map(|r| self.row(r))
+    }
+
+    /// a zero-width matrix has no values to chunk, but still has a row per row
+    pub fn par_rows_mut(&mut self) -> impl IndexedParallelIterator<Item = &mut [M31]> {
+        if self.w == 0 {
+            Either::Left((0..self.h).into_par_iter().map(|_| <&mut [M31]>::default()))
+        } else {
+            Either::Right(self.vals.par_chunks_exact_mut(self.w))
+        }
+    }
+
+    /// One accumulator per row, fed the row left to right in `CHUNK`-wide pieces
+    /// (the last one may be short). `init` gets the row as a one-row range, to match
+    /// `TMat::fold_rows`.
+    pub fn fold_rows<Acc, Init, Op>(&self, init: Init, op: Op) -> Vec<Acc>
+    where
+        Acc: Send + Sync,
+        Init: Fn(Range<usize>) -> Acc + Send + Sync,
+        Op: Fn(Acc, &[M31]) -> Acc + Send + Sync,
+    {
+        self.par_rows()
+            .enumerate()
+            .map(|(r, row)| row.chunks(CHUNK).fold(init(r..r + 1), &op))
+            .collect()
+    }
+
+    /// One accumulator per `CHUNK`-wide column strip, fed each row's piece of the
+    /// strip top to bottom. `init` gets the column range of the strip.
+    pub fn fold_cols<Acc, Init, Op>(&self, init: Init, op: Op) -> Vec<Acc>
+    where
+        Acc: Send + Sync,
+        Init: Fn(Range<usize>) -> Acc + Send + Sync,
+        Op: Fn(Acc, &[M31]) -> Acc + Send + Sync,
+    {
+        let w = self.w;
+        (0..w.div_ceil(CHUNK))
+            .into_par_iter()
+            .map(|strip| {
+                let cols = strip * CHUNK..cmp::min((strip + 1) * CHUNK, w);
+                self.vals
+                    .chunks_exact(w)
+                    .map(|row| &row[cols.clone()])
+                    .fold(init(cols.clone()), &op)
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for RmMat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for r in 0..self.h {
+            for elt in self.row(r) {
+                write!(f, "{elt:?} ")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+    use crate::tiled_mat::TMat;
+
+    fn rand_mats(h: usize, w: usize) -> (RmMat, TMat<M31, 2>) {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let vals: Vec<M31> = (0..h * w).map(|_| rng.gen()).collect();
+        let rm = RmMat::from_fn(h, w, |r, c| vals[r * w + c]);
+        let tm = TMat::from_fn(h, w, |r, c| vals[r * w + c]);
+        (rm, tm)
+    }
+
+    #[test]
+    fn access_matches_tmat() {
+        let (h, w) = (37, 11);
+        let (mut rm, tm) = rand_mats(h, w);
+        assert_eq!((rm.height(), rm.width()), (h, w));
+        for r in 0..h {
+            assert!(rm.row(r).iter().copied().eq(tm.row(r)));
+        }
+        rm.set(h - 1, w - 1, M31::ONE);
+        assert_eq!(rm.get(h - 1, w - 1), M31::ONE);
+    }
+
+    #[test]
+    fn folds_match_tmat() {
+        let (h, w) = (37, 70);
+        let (rm, tm) = rand_mats(h, w);
+
+        let row_sums = rm.fold_rows(
+            |rows| (rows, M31::ZERO),
+            |(rows, acc), xs| (rows, acc + xs.iter().copied().sum()),
+        );
+        assert_eq!(row_sums.len(), h);
+        for (r, (rows, sum)) in row_sums.into_iter().enumerate() {
+            assert_eq!(rows, r..r + 1);
+            assert_eq!(sum, tm.row(r).sum());
+        }
+
+        let strips = rm.fold_cols(
+            |cols| (cols.clone(), vec![M31::ZERO; cols.len()]),
+            |(cols, mut acc), xs| {
+                for (a, x) in acc.iter_mut().zip(xs) {
+                    *a += *x;
+                }
+                (cols, acc)
+            },
+        );
+        let col_sums: Vec<M31> = strips.into_iter().flat_map(|(_, acc)| acc).collect();
+        let expected: Vec<M31> = (0..w).map(|c| tm.col(c).sum()).collect();
+        assert_eq!(col_sums, expected);
+    }
+
+    #[test]
+    fn zero_dimensions() {
+        for (h, w) in [(0, 5), (5, 0), (0, 0)] {
+            let mut rm = RmMat::zero(h, w);
+            assert_eq!(rm.par_rows().count(), h);
+            assert_eq!(rm.par_rows_mut().count(), h);
+            assert_eq!(rm.fold_rows(|rows| rows, |rows, _| rows).len(), h);
+            let strips = rm.fold_cols(|cols| cols, |cols, _| cols);
+            assert_eq!(strips.len(), w.div_ceil(CHUNK));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn get_out_of_bounds() {
+        RmMat::zero(8, 8).get(0, 8);
+    }
+
+    #[test]
+    #[should_panic]
+    fn row_out_of_bounds() {
+        RmMat::zero(5, 0).row(5);
+    }
+}
@@ -0,0 +1,240 @@
+//! Tiles stored in some order other than row-major. Everything but the order is
+//! shared: a layout is a [`TileMap`] saying where each tile of the grid goes, and
+//! [`MappedMat`] does the rest.
+
+use std::{cmp, fmt, mem, ops::Range};
+
+use rayon::prelude::*;
+
+use crate::{
+    packable::Packable,
+    tiled_mat::{TMat, Tile},
+};
+
+/// Where the tiles of a `tile_rows x tiles_per_row` grid are stored.
+pub trait TileMap: Clone + Send + Sync + 'static {
+    fn new(tile_rows: usize, tiles_per_row: usize) -> Self;
+    /// tiles to allocate, padding included
+    fn capacity(&self) -> usize;
+    /// where tile `(tr, tc)` lives in the tile buffer
+    fn tile_index(&self, tr: usize, tc: usize) -> usize;
+}
+
+/// `width` and `height` are logical, as in `TMat`. Tiles the map pads the grid
+/// out with are zero.
+#[derive(Clone)]
+pub struct MappedMat<T, const LTW: usize, M> {
+    pub width: usize,
+    pub height: usize,
+    tile_rows: usize,
+    tiles_per_row: usize,
+    map: M,
+    tiles: Vec<Tile<T, LTW>>,
+}
+
+impl<T: Packable, const LTW: usize, M: TileMap> MappedMat<T, LTW, M> {
+    pub fn zero(height: usize, width: usize) -> Self {
+        let lth = Tile::<T, LTW>::LTH;
+        let tile_rows = height.div_ceil(1 << lth);
+        let tiles_per_row = width.div_ceil(1 << LTW);
+        let map = M::new(tile_rows, tiles_per_row);
+        Self {
+            width,
+            height,
+            tile_rows,
+            tiles_per_row,
+            tiles: vec![Tile::zero(); map.capacity()],
+            map,
+        }
+    }
+
+    pub fn from_fn(height: usize, width: usize, mut f: impl FnMut(usize, usize) -> T) -> Self {
+        let lth = Tile::<T, LTW>::LTH;
+        let mut m = Self::zero(height, width);
+        for tr in 0..m.tile_rows {
+            for tc in 0..m.tiles_per_row {
+                let (r0, c0) = (tr << lth, tc << LTW);
+                let i = m.tile_index(tr, tc);
+                m.tiles[i] = Tile::from_fn_clipped(height - r0, width - c0, |rit, cit| {
+                    f(r0 + rit, c0 + cit)
+                });
+            }
+        }
+        m
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn tile_rows(&self) -> usize {
+        self.tile_rows
+    }
+
+    pub fn tiles_per_row(&self) -> usize {
+        self.tiles_per_row
+    }
+
+    pub fn map(&self) -> &M {
+        &self.map
+    }
+
+    /// every stored tile in the map's order, padding included
+    pub fn tiles(&self) -> &[Tile<T, LTW>] {
+        &self.tiles
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.tiles.len() * mem::size_of::<Tile<T, LTW>>()
+    }
+
+    /// where tile `(tr, tc)` lives in `tiles()`
+    pub fn tile_index(&self, tr: usize, tc: usize) -> usize {
+        self.map.tile_index(tr, tc)
+    }
+
+    pub fn tile(&self, tr: usize, tc: usize) -> &Tile<T, LTW> {
+        &self.tiles[self.tile_index(tr, tc)]
+    }
+
+    fn check_bounds(&self, r: usize, c: usize) {
+        let (h, w) = (self.height, self.width);
+        assert!(
+            r < h && c < w,
+            "({r}, {c}) out of bounds for {h}x{w} matrix"
+        );
+    }
+
+    pub fn get(&self, r: usize, c: usize) -> T {
+        self.check_bounds(r, c);
+        let lth = Tile::<T, LTW>::LTH;
+        self.tile(r >> lth, c >> LTW)
+            .get(r & ((1 << lth) - 1), c & ((1 << LTW) - 1))
+    }
+
+    pub fn set(&mut self, r: usize, c: usize, val: T) {
+        self.check_bounds(r, c);
+        let lth = Tile::<T, LTW>::LTH;
+        let i = self.tile_index(r >> lth, c >> LTW);
+        let (rit, cit) = (r & ((1 << lth) - 1), c & ((1 << LTW) - 1));
+        self.tiles[i].as_mut_slice()[(rit << LTW) + cit] = val;
+    }
+
+    /// Same contract as `TMat::fold_rows`: one accumulator per tile row, `init` gets
+    /// the logical row range, `op` sees whole tiles.
+    pub fn fold_rows<Acc, Init, Op>(&self, init: Init, op: Op) -> Vec<Acc>
+    where
+        Acc: Send + Sync,
+        Init: Fn(Range<usize>) -> Acc + Send + Sync,
+        Op: Fn(Acc, &Tile<T, LTW>) -> Acc + Send + Sync,
+    {
+        let lth = Tile::<T, LTW>::LTH;
+        (0..self.tile_rows)
+            .into_par_iter()
+            .map(|tr| {
+                let r0 = tr << lth;
+                let acc = init(r0..cmp::min(r0 + (1 << lth), self.height));
+                (0..self.tiles_per_row).fold(acc, |acc, tc| op(acc, self.tile(tr, tc)))
+            })
+            .collect()
+    }
+
+    /// Same contract as `TMat::fold_cols`.
+    pub fn fold_cols<Acc, Init, Op>(&self, init: Init, op: Op) -> Vec<Acc>
+    where
+        Acc: Send + Sync,
+        Init: Fn(Range<usize>) -> Acc + Send + Sync,
+        Op: Fn(Acc, &Tile<T, LTW>) -> Acc + Send + Sync,
+    {
+        (0..self.tiles_per_row)
+            .into_par_iter()
+            .map(|tc| {
+                let c0 = tc << LTW;
+                let acc = init(c0..cmp::min(c0 + (1 << LTW), self.width));
+                (0..self.tile_rows).fold(acc, |acc, tr| op(acc, self.tile(tr, tc)))
+            })
+            .collect()
+    }
+}
+
+impl<T: Packable, const LTW: usize, M: TileMap> From<&TMat<T, LTW>> for MappedMat<T, LTW, M> {
+    fn from(m: &TMat<T, LTW>) -> Self {
+        let mut out = Self::zero(m.height, m.width);
+        let tpr = out.tiles_per_row;
+        for (i, tile) in m.tiles.iter().enumerate() {
+            let j = out.tile_index(i / tpr, i % tpr);
+            out.tiles[j] = *tile;
+        }
+        out
+    }
+}
+
+impl<T: Packable, const LTW: usize, M: TileMap> From<&MappedMat<T, LTW, M>> for TMat<T, LTW> {
+    fn from(m: &MappedMat<T, LTW, M>) -> Self {
+        let tpr = m.tiles_per_row;
+        TMat {
+            width: m.width,
+            height: m.height,
+            tiles: (0..m.tile_rows * tpr)
+                .map(|i| *m.tile(i / tpr, i % tpr))
+                .collect(),
+        }
+    }
+}
+
+impl<T: Packable + fmt::Debug, const LTW: usize, M: TileMap> fmt::Debug for MappedMat<T, LTW, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for r in 0..self.height {
+            for c in 0..self.width {
+                write!(f, "{:?} ", self.get(r, c))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        blocked::BlockMap,
+        curve::{CurveMap, Hilbert, Morton},
+    };
+
+    fn check_against_tmat<const LTW: usize, M: TileMap>(h: usize, w: usize) {
+        let f = |r: usize, c: usize| (r * 1000 + c) as u32;
+        let tm = TMat::<u32, LTW>::from_fn(h, w, f);
+        let mut mm = MappedMat::<u32, LTW, M>::from_fn(h, w, f);
+        for r in 0..h {
+            for c in 0..w {
+                assert_eq!(mm.get(r, c), f(r, c));
+            }
+        }
+        assert_eq!(TMat::from(&mm).tiles, tm.tiles);
+        assert_eq!(MappedMat::<u32, LTW, M>::from(&tm).tiles, mm.tiles);
+
+        let sum =
+            |acc: u32, t: &Tile<u32, LTW>| t.as_slice().iter().fold(acc, |a, x| a.wrapping_add(*x));
+        assert_eq!(mm.fold_rows(|_| 0, sum), tm.fold_rows(|_| 0, sum));
+        assert_eq!(mm.fold_cols(|_| 0, sum), tm.fold_cols(|_| 0, sum));
+
+        mm.set(h - 1, w - 1, 7);
+        assert_eq!(mm.get(h - 1, w - 1), 7);
+    }
+
+    #[test]
+    fn matches_tmat() {
+        for (h, w) in [(64, 64), (37, 11), (1000, 37), (5, 300)] {
+            check_against_tmat::<0, CurveMap<Morton>>(h, w);
+            check_against_tmat::<2, CurveMap<Morton>>(h, w);
+            check_against_tmat::<4, CurveMap<Morton>>(h, w);
+            check_against_tmat::<2, CurveMap<Hilbert>>(h, w);
+            check_against_tmat::<3, CurveMap<Hilbert>>(h, w);
+            check_against_tmat::<2, BlockMap<0, 0, false, false>>(h, w);
+            check_against_tmat::<2, BlockMap<2, 1, true, false>>(h, w);
+            check_against_tmat::<2, BlockMap<1, 2, false, true>>(h, w);
+            check_against_tmat::<2, BlockMap<3, 3, true, true>>(h, w);
+        }
+    }
+}
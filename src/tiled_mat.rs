@@ -1,77 +1,201 @@
-use std::{
-    arch::aarch64::uint32x4_t,
-    array, cmp, fmt, iter,
-    mem::{self, transmute},
-    ops::Range,
-};
+use std::{cmp, fmt, iter::StepBy, marker::PhantomData, mem, ops::Range, slice};
 
 use itertools::iproduct;
-use rayon::prelude::*;
+use rayon::{iter::Either, prelude::*};
+
+use crate::{
+    lanes::{self, Lane, LANES_PER_TILE},
+    packable::Packable,
+    packed_m31::PackedM31,
+    tinym31::M31,
+};
 
-#[derive(Copy, Clone, Debug)]
+/// One cache line of `T`s, `1 << LTW` wide and `1 << LTH` tall, row-major inside.
+#[derive(Copy, Clone, PartialEq, Eq)]
 #[repr(C, align(64))]
-pub struct Tile<const LTW: usize>([u32; 16]);
+pub struct Tile<T, const LTW: usize>([u8; 64], PhantomData<T>);
+
+const _: () = {
+    assert!(mem::size_of::<Tile<u8, 0>>() == 64);
+    assert!(mem::align_of::<Tile<u8, 0>>() == 64);
+};
+
+/// tile columns per task in the transposes
+const TRANSPOSE_BAND: usize = 16;
 
 const fn mask(bits: usize) -> usize {
     (1 << bits) - 1
 }
 
-impl<const LTW: usize> Tile<LTW> {
-    const LTH: usize = 4 - LTW;
-    pub fn from_fn(mut f: impl FnMut(usize, usize) -> u32) -> Self {
-        Self(array::from_fn(|i| f(i >> LTW, i & mask(LTW))))
+impl<T: Packable, const LTW: usize> Tile<T, LTW> {
+    pub const LTH: usize = T::LOG_PER_TILE - LTW;
+
+    pub fn from_fn(mut f: impl FnMut(usize, usize) -> T) -> Self {
+        let mut t = Self::zero();
+        for (i, x) in t.as_mut_slice().iter_mut().enumerate() {
+            *x = f(i >> LTW, i & mask(LTW));
+        }
+        t
+    }
+    /// only calls `f` inside the top-left `rows` x `cols`, the rest stays zero
+    pub fn from_fn_clipped(rows: usize, cols: usize, mut f: impl FnMut(usize, usize) -> T) -> Self {
+        let mut t = Self::zero();
+        let s = t.as_mut_slice();
+        for rit in 0..cmp::min(rows, 1 << Self::LTH) {
+            for cit in 0..cmp::min(cols, 1 << LTW) {
+                s[(rit << LTW) + cit] = f(rit, cit);
+            }
+        }
+        t
     }
     pub fn zero() -> Self {
-        Tile([0; 16])
+        Tile([0; 64], PhantomData)
+    }
+    pub fn get(&self, rit: usize, cit: usize) -> T {
+        self.as_slice()[(rit << LTW) + cit]
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.0.as_ptr() as *const T, 1 << T::LOG_PER_TILE) }
+    }
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.0.as_mut_ptr() as *mut T, 1 << T::LOG_PER_TILE) }
     }
 
     /// if you don't care about arrangement
-    pub fn vecs(&self) -> &[uint32x4_t; 4] {
-        unsafe { &*(&self.0 as *const [u32; 16] as *const [uint32x4_t; 4]) }
+    pub fn vecs(&self) -> &[Lane; LANES_PER_TILE] {
+        unsafe { &*(&self.0 as *const [u8; 64] as *const [Lane; LANES_PER_TILE]) }
+    }
+    pub fn vecs_mut(&mut self) -> &mut [Lane; LANES_PER_TILE] {
+        unsafe { &mut *(&mut self.0 as *mut [u8; 64] as *mut [Lane; LANES_PER_TILE]) }
     }
-    pub fn vecs_mut(&mut self) -> &mut [uint32x4_t; 4] {
-        unsafe { &mut *(&mut self.0 as *mut [u32; 16] as *mut [uint32x4_t; 4]) }
+
+    /// The same elements as a `(1 << LTW) x (1 << LTH)` tile, so `O_LTW` has to be
+    /// `LTH`. 4x4 tiles of 4-byte elements go through the lane shuffles.
+    pub fn transposed<const O_LTW: usize>(&self) -> Tile<T, O_LTW> {
+        assert_eq!(
+            O_LTW,
+            Self::LTH,
+            "transposed tiles are {} wide",
+            1 << Self::LTH
+        );
+        if LTW == 0 || Self::LTH == 0 {
+            // a single row or column: same elements in the same order
+            return Tile(self.0, PhantomData);
+        }
+        if mem::size_of::<T>() == 4 && LTW == 2 {
+            let mut out = Tile::zero();
+            *out.vecs_mut() = lanes::transpose_4x4(*self.vecs());
+            return out;
+        }
+        Tile::from_fn(|rit, cit| self.get(cit, rit))
+    }
+}
+
+impl<const LTW: usize> Tile<u32, LTW> {
+    pub fn wrapping_add_assign(&mut self, rhs: &Self) {
+        for (l, r) in self.vecs_mut().iter_mut().zip(rhs.vecs()) {
+            *l = lanes::add(*l, *r);
+        }
+    }
+}
+
+impl<const LTW: usize> Tile<M31, LTW> {
+    pub fn packed(&self) -> &[PackedM31; LANES_PER_TILE] {
+        unsafe { &*(self.vecs() as *const [Lane; LANES_PER_TILE] as *const _) }
+    }
+    pub fn packed_mut(&mut self) -> &mut [PackedM31; LANES_PER_TILE] {
+        unsafe { &mut *(self.vecs_mut() as *mut [Lane; LANES_PER_TILE] as *mut _) }
+    }
+
+    /// the canonical values, for kernels that work on raw `u32` lanes
+    pub fn values(&self) -> &[u32; 16] {
+        unsafe { &*(&self.0 as *const [u8; 64] as *const [u32; 16]) }
+    }
+
+    pub fn mul_assign(&mut self, rhs: &Self) {
+        for (l, r) in self.packed_mut().iter_mut().zip(rhs.packed()) {
+            *l *= *r;
+        }
     }
 }
 
+impl<T: Packable + fmt::Debug, const LTW: usize> fmt::Debug for Tile<T, LTW> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.as_slice()).finish()
+    }
+}
+
+/// `width` and `height` are the logical dimensions. Edge tiles are padded out to a
+/// full tile; `from_fn` fills the padding with zeros.
 #[derive(Clone)]
-pub struct TMat<const LTW: usize> {
+pub struct TMat<T, const LTW: usize> {
     pub width: usize,
-    pub tiles: Vec<Tile<LTW>>,
+    pub height: usize,
+    pub tiles: Vec<Tile<T, LTW>>,
 }
 
-impl<const LTW: usize> TMat<LTW> {
-    const fn tiles_per_row(&self) -> usize {
-        self.width >> LTW
+impl<T: Packable, const LTW: usize> TMat<T, LTW> {
+    pub(crate) const fn tiles_per_row(&self) -> usize {
+        self.width.div_ceil(1 << LTW)
     }
 
     pub fn bytes(&self) -> usize {
-        self.tiles.len() * mem::size_of::<Tile<LTW>>()
+        self.tiles.len() * mem::size_of::<Tile<T, LTW>>()
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// (tile index, index within tile)
+    fn locate(&self, r: usize, c: usize) -> (usize, usize) {
+        let lth = Tile::<T, LTW>::LTH;
+        let (tr, rit) = (r >> lth, r & mask(lth));
+        let (tc, cit) = (c >> LTW, c & mask(LTW));
+        (self.tiles_per_row() * tr + tc, (rit << LTW) + cit)
+    }
+
+    fn check_bounds(&self, r: usize, c: usize) {
+        let (h, w) = (self.height(), self.width);
+        assert!(
+            r < h && c < w,
+            "({r}, {c}) out of bounds for {h}x{w} matrix"
+        );
     }
 
     #[allow(non_snake_case)]
-    pub fn from_fn(height: usize, width: usize, mut f: impl FnMut(usize, usize) -> u32) -> Self {
-        let LTH = Tile::<LTW>::LTH;
+    pub fn from_fn(height: usize, width: usize, mut f: impl FnMut(usize, usize) -> T) -> Self {
+        let LTH = Tile::<T, LTW>::LTH;
+        let tile_rows = height.div_ceil(1 << LTH);
+        let tpr = width.div_ceil(1 << LTW);
         Self {
             width,
-            tiles: iproduct!(0..(height >> LTH), 0..(width >> LTW))
-                .map(|(tr, tc)| Tile::from_fn(|rit, cit| f((tr << LTH) + rit, (tc << LTW) + cit)))
+            height,
+            tiles: iproduct!(0..tile_rows, 0..tpr)
+                .map(|(tr, tc)| {
+                    let (r0, c0) = (tr << LTH, tc << LTW);
+                    Tile::from_fn_clipped(height - r0, width - c0, |rit, cit| f(r0 + rit, c0 + cit))
+                })
                 .collect(),
         }
     }
 
+    /// One accumulator per tile row, fed left to right. `init` gets the logical row
+    /// range of the band. `op` sees whole tiles, so edge tiles include their padding.
     pub fn fold_rows<Acc, Init, Op>(&self, init: Init, op: Op) -> Vec<Acc>
     where
         Acc: Send + Sync,
         Init: Fn(Range<usize>) -> Acc + Send + Sync,
-        Op: Fn(Acc, &Tile<LTW>) -> Acc + Send + Sync,
+        Op: Fn(Acc, &Tile<T, LTW>) -> Acc + Send + Sync,
     {
-        let tpr = self.tiles_per_row();
-        self.tiles
-            .par_chunks_exact(tpr)
+        let lth = Tile::<T, LTW>::LTH;
+        let height = self.height;
+        self.par_row_tiles_native()
             .enumerate()
             .map(|(tr, tile_row)| {
-                let mut acc = init(tr * tpr..(tr + 1) * tpr);
+                let r0 = tr << lth;
+                let mut acc = init(r0..cmp::min(r0 + (1 << lth), height));
                 for t in tile_row {
                     acc = op(acc, t);
                 }
@@ -80,81 +204,521 @@ impl<const LTW: usize> TMat<LTW> {
             .collect()
     }
 
-    pub fn par_row_tiles_native(&self) -> impl IndexedParallelIterator<Item = &[Tile<LTW>]> {
-        let tpr = self.tiles_per_row();
-        self.tiles.par_chunks_exact(tpr)
+    /// Like `fold_rows`, but one accumulator per tile column, fed top to bottom.
+    /// `init` gets the logical column range of the strip.
+    pub fn fold_cols<Acc, Init, Op>(&self, init: Init, op: Op) -> Vec<Acc>
+    where
+        Acc: Send + Sync,
+        Init: Fn(Range<usize>) -> Acc + Send + Sync,
+        Op: Fn(Acc, &Tile<T, LTW>) -> Acc + Send + Sync,
+    {
+        let width = self.width;
+        self.par_col_tiles()
+            .enumerate()
+            .map(|(tc, col)| {
+                let c0 = tc << LTW;
+                col.fold(init(c0..cmp::min(c0 + (1 << LTW), width)), &op)
+            })
+            .collect()
+    }
+
+    /// Applies `f` to every tile in parallel. Padding in the result is whatever `f`
+    /// made of it, so it's only zero if `f` maps zero to zero.
+    pub fn map_tiles(&self, f: impl Fn(&Tile<T, LTW>) -> Tile<T, LTW> + Send + Sync) -> Self {
+        Self {
+            width: self.width,
+            height: self.height,
+            tiles: self.tiles.par_iter().map(f).collect(),
+        }
+    }
+
+    /// Calls `f` on each pair of corresponding tiles in parallel, e.g.
+    /// `a.zip_tiles_mut(&b, |x, y| x.wrapping_add_assign(y))`. Same caveat about
+    /// padding as `map_tiles`.
+    pub fn zip_tiles_mut(
+        &mut self,
+        other: &Self,
+        f: impl Fn(&mut Tile<T, LTW>, &Tile<T, LTW>) + Send + Sync,
+    ) {
+        assert_eq!(
+            (self.height, self.width),
+            (other.height, other.width),
+            "dimension mismatch"
+        );
+        self.tiles
+            .par_iter_mut()
+            .zip(&other.tiles)
+            .for_each(|(a, b)| f(a, b));
+    }
+
+    pub fn par_row_tiles_native(&self) -> impl IndexedParallelIterator<Item = &[Tile<T, LTW>]> {
+        let tile_rows = self.height.div_ceil(1 << Tile::<T, LTW>::LTH);
+        (0..tile_rows).into_par_iter().map(|tr| self.tile_row(tr))
     }
 
+    /// a zero-width matrix has rows but no tiles, so each of its tile rows is empty
     pub fn par_row_tiles_native_mut(
         &mut self,
-    ) -> impl IndexedParallelIterator<Item = &mut [Tile<LTW>]> {
+    ) -> impl IndexedParallelIterator<Item = &mut [Tile<T, LTW>]> {
         let tpr = self.tiles_per_row();
-        self.tiles.par_chunks_exact_mut(tpr)
+        if tpr == 0 {
+            let tile_rows = self.height.div_ceil(1 << Tile::<T, LTW>::LTH);
+            Either::Left(
+                (0..tile_rows)
+                    .into_par_iter()
+                    .map(|_| <&mut [_]>::default()),
+            )
+        } else {
+            Either::Right(self.tiles.par_chunks_exact_mut(tpr))
+        }
+    }
+
+    /// one item per tile column, walking its tiles top to bottom
+    pub fn par_col_tiles(
+        &self,
+    ) -> impl IndexedParallelIterator<Item = StepBy<slice::Iter<'_, Tile<T, LTW>>>> {
+        let tpr = self.tiles_per_row();
+        (0..tpr)
+            .into_par_iter()
+            // a zero-height matrix has columns but no tiles
+            .map(move |tc| {
+                self.tiles[cmp::min(tc, self.tiles.len())..]
+                    .iter()
+                    .step_by(tpr)
+            })
     }
 
+    /// Re-tiles on the fly: each item walks one row band (tall enough for both tile
+    /// shapes) and yields its `Tile<T, O_LTW>`s in row-major order. Output tiles are
+    /// zero-padded past the logical edges, same as `TMat::<T, O_LTW>::from_fn`.
     pub fn par_row_tiles<const O_LTW: usize>(
         &self,
-    ) -> impl IndexedParallelIterator<Item = TileIter<'_, LTW, O_LTW>> {
+    ) -> impl IndexedParallelIterator<Item = TileIter<'_, T, LTW, O_LTW>> {
         let tile_rows_per_iter = 1 << LTW.saturating_sub(O_LTW);
+        let band_height = tile_rows_per_iter << Tile::<T, LTW>::LTH;
+        let (height, width) = (self.height, self.width);
         let tpr = self.tiles_per_row();
-        self.tiles
-            .par_chunks_exact(tile_rows_per_iter * tpr)
-            .map(move |chunk| TileIter {
-                idx: 0,
-                chunk,
-                tile_rows: tile_rows_per_iter,
+        let band_tiles = tile_rows_per_iter * tpr;
+        // counted in rows rather than tiles, since a zero-width matrix has no tiles
+        (0..height.div_ceil(band_height))
+            .into_par_iter()
+            .map(move |band| {
+                let t0 = band * band_tiles;
+                TileIter {
+                    idx: 0,
+                    chunk: &self.tiles[t0..cmp::min(t0 + band_tiles, self.tiles.len())],
+                    tiles_per_row: tpr,
+                    rows: cmp::min(band_height, height - band * band_height),
+                    width,
+                }
             })
     }
 
-    /*
+    /// The whole matrix in `O_LTW`-wide tiles. A column of `2^|LTW - O_LTW|` wide
+    /// tiles covers the same block as a row of as many narrow ones, and each row
+    /// of the block is split into (or joined from) narrow-tile-wide runs, so every
+    /// group converts on its own. Groups cut by the matrix edge are gathered
+    /// element by element.
+    pub fn retile<const O_LTW: usize>(&self) -> TMat<T, O_LTW> {
+        let (height, width) = (self.height, self.width);
+        if LTW == O_LTW {
+            return TMat {
+                width,
+                height,
+                tiles: self.tiles.iter().map(|t| Tile(t.0, PhantomData)).collect(),
+            };
+        }
+        // 4-byte column tiles are one 4x4 transpose away from LTW = 2, so go
+        // through there rather than shuffling single elements
+        if mem::size_of::<T>() == 4 && cmp::min(LTW, O_LTW) == 0 && cmp::max(LTW, O_LTW) > 2 {
+            return self.retile::<2>().retile::<O_LTW>();
+        }
+
+        let (i_lth, o_lth) = (Tile::<T, LTW>::LTH, Tile::<T, O_LTW>::LTH);
+        let band_lth = cmp::max(i_lth, o_lth);
+        let (i_tpr, o_tpr) = (self.tiles_per_row(), width.div_ceil(1 << O_LTW));
+        let k = 1 << LTW.abs_diff(O_LTW);
+        // bytes per run: one row of a narrow tile
+        let run = mem::size_of::<T>() << cmp::min(LTW, O_LTW);
+
+        let mut tiles = vec![Tile::zero(); height.div_ceil(1 << o_lth) * o_tpr];
+        if o_tpr == 0 {
+            return TMat {
+                width,
+                height,
+                tiles,
+            };
+        }
+        tiles
+            .par_chunks_mut(o_tpr << (band_lth - o_lth))
+            .zip(self.tiles.par_chunks(i_tpr << (band_lth - i_lth)))
+            .enumerate()
+            .for_each(|(band, (out, inp))| {
+                let r_base = band << band_lth;
+                let full_band = r_base + (1 << band_lth) <= height;
+                // group g is wide tile column g and narrow tiles `g * k..(g + 1) * k`
+                let (groups, narrow_tpr) = if LTW > O_LTW {
+                    (i_tpr, o_tpr)
+                } else {
+                    (o_tpr, i_tpr)
+                };
+                for g in 0..groups {
+                    if !full_band || (g + 1) * k > narrow_tpr {
+                        for (otr, out_row) in out.chunks_exact_mut(o_tpr).enumerate() {
+                            let otcs = if LTW > O_LTW {
+                                g * k..cmp::min((g + 1) * k, o_tpr)
+                            } else {
+                                g..g + 1
+                            };
+                            for otc in otcs {
+                                let (r0, c0) = (r_base + (otr << o_lth), otc << O_LTW);
+                                out_row[otc] = Tile::from_fn_clipped(
+                                    height - r0,
+                                    width - c0,
+                                    |rit, cit| unsafe { self.get_unchecked(r0 + rit, c0 + cit) },
+                                );
+                            }
+                        }
+                        continue;
+                    }
+                    if mem::size_of::<T>() == 4 && LTW + O_LTW == 2 {
+                        // 4x4 <-> 16x1: the column tiles' 4-element pieces are the
+                        // rows of the transposed 4x4 tiles
+                        for j in 0..4 {
+                            if LTW == 2 {
+                                let t = Tile::<T, 2>(inp[j * i_tpr + g].0, PhantomData)
+                                    .transposed::<2>();
+                                for m in 0..4 {
+                                    out[g * 4 + m].0[j * 16..][..16]
+                                        .copy_from_slice(&t.0[m * 16..][..16]);
+                                }
+                            } else {
+                                let mut t = Tile::<T, 2>::zero();
+                                for m in 0..4 {
+                                    t.0[m * 16..][..16]
+                                        .copy_from_slice(&inp[g * 4 + m].0[j * 16..][..16]);
+                                }
+                                out[j * o_tpr + g] = Tile(t.transposed::<2>().0, PhantomData);
+                            }
+                        }
+                        continue;
+                    }
+                    let w_lth = cmp::min(i_lth, o_lth);
+                    let w_ltw = cmp::max(LTW, O_LTW);
+                    for r in 0..1 << band_lth {
+                        // row r of the group: wide tile `r >> w_lth`, narrow run `r`
+                        let (j, w_off) = (r >> w_lth, (r & mask(w_lth)) << w_ltw);
+                        for m in 0..k {
+                            let w_at = w_off * mem::size_of::<T>() + m * run;
+                            if LTW > O_LTW {
+                                out[g * k + m].0[r * run..][..run]
+                                    .copy_from_slice(&inp[j * i_tpr + g].0[w_at..][..run]);
+                            } else {
+                                out[j * o_tpr + g].0[w_at..][..run]
+                                    .copy_from_slice(&inp[g * k + m].0[r * run..][..run]);
+                            }
+                        }
+                    }
+                }
+            });
+        TMat {
+            width,
+            height,
+            tiles,
+        }
+    }
+
     pub fn zero(height: usize, width: usize) -> Self {
-        Self::from_fn(height, width, |_, _| 0)
+        Self::from_fn(height, width, |_, _| T::zeroed())
     }
-    fn tile_row_mut(&mut self, tr: usize) -> &mut [Tile] {
+
+    pub fn tile_row(&self, tr: usize) -> &[Tile<T, LTW>] {
         let tpr = self.tiles_per_row();
-        &mut self.tiles[(tr * tpr)..((tr + 1) * tpr)]
+        &self.tiles[(tr * tpr)..((tr + 1) * tpr)]
+    }
+
+    pub fn get(&self, r: usize, c: usize) -> T {
+        self.check_bounds(r, c);
+        unsafe { self.get_unchecked(r, c) }
+    }
+
+    pub fn get_mut(&mut self, r: usize, c: usize) -> &mut T {
+        self.check_bounds(r, c);
+        unsafe { self.get_unchecked_mut(r, c) }
+    }
+
+    pub fn set(&mut self, r: usize, c: usize, val: T) {
+        *self.get_mut(r, c) = val;
+    }
+
+    /// # Safety
+    /// `r < self.height()` and `c < self.width`
+    pub unsafe fn get_unchecked(&self, r: usize, c: usize) -> T {
+        let (t, i) = self.locate(r, c);
+        *self.tiles.get_unchecked(t).as_slice().get_unchecked(i)
+    }
+
+    /// # Safety
+    /// `r < self.height()` and `c < self.width`
+    pub unsafe fn get_unchecked_mut(&mut self, r: usize, c: usize) -> &mut T {
+        let (t, i) = self.locate(r, c);
+        self.tiles
+            .get_unchecked_mut(t)
+            .as_mut_slice()
+            .get_unchecked_mut(i)
+    }
+
+    /// # Safety
+    /// `r < self.height()` and `c < self.width`
+    pub unsafe fn set_unchecked(&mut self, r: usize, c: usize, val: T) {
+        *self.get_unchecked_mut(r, c) = val;
+    }
+
+    pub fn row(&self, r: usize) -> RowIter<'_, T, LTW> {
+        assert!(r < self.height, "row {r} out of bounds");
+        let lth = Tile::<T, LTW>::LTH;
+        RowIter {
+            tiles: self.tile_row(r >> lth),
+            rit: r & mask(lth),
+            idx: 0,
+            width: self.width,
+        }
+    }
+
+    /// the contiguous pieces of row `r`, one per tile; the last one stops at the
+    /// logical width
+    pub fn row_segments(&self, r: usize) -> impl Iterator<Item = &[T]> + '_ {
+        assert!(r < self.height, "row {r} out of bounds");
+        let lth = Tile::<T, LTW>::LTH;
+        let rit = r & mask(lth);
+        let width = self.width;
+        self.tile_row(r >> lth)
+            .iter()
+            .enumerate()
+            .map(move |(tc, t)| {
+                let len = cmp::min(1 << LTW, width - (tc << LTW));
+                &t.as_slice()[(rit << LTW)..((rit << LTW) + len)]
+            })
+    }
+
+    pub fn col(&self, c: usize) -> impl Iterator<Item = T> + '_ {
+        assert!(c < self.width, "column {c} out of bounds");
+        let cit = c & mask(LTW);
+        // a zero-height matrix has columns but no tiles
+        self.tiles[cmp::min(c >> LTW, self.tiles.len())..]
+            .iter()
+            .step_by(self.tiles_per_row())
+            .flat_map(move |t| t.as_slice()[cit..].iter().step_by(1 << LTW).copied())
+            .take(self.height)
     }
-    pub fn get_mut(&mut self, r: usize, c: usize) -> &mut u32 {
-        let (tr, rit) = (r >> Self::LTH, r & mask(Self::LTH));
-        let (tc, cit) = (c >> LTW, c & mask(LTW));
 
+    /// Tile-for-tile transpose: output tile `(tc, tr)` is input tile `(tr, tc)`
+    /// transposed, so `O_LTW` has to be `LTH`. For `u32` that turns row tiles
+    /// (LTW = 4) into column tiles (LTW = 0) and back.
+    pub fn transpose_reshaped<const O_LTW: usize>(&self) -> TMat<T, O_LTW> {
+        let lth = Tile::<T, LTW>::LTH;
+        assert_eq!(O_LTW, lth, "transposed tiles are {} wide", 1 << lth);
         let tpr = self.tiles_per_row();
-        let tile = &mut self.tiles[tpr * tr + tc];
+        let tile_rows = self.height.div_ceil(1 << lth);
+        let mut tiles = vec![Tile::zero(); tpr * tile_rows];
+        if tile_rows > 0 {
+            // a band of input tile columns per task, read a short run of each row
+            // at a time instead of one tile per row
+            tiles
+                .par_chunks_mut(tile_rows * TRANSPOSE_BAND)
+                .enumerate()
+                .for_each(|(band, out)| {
+                    let tc0 = band * TRANSPOSE_BAND;
+                    for (tr, in_row) in self.tiles.chunks_exact(tpr).enumerate() {
+                        for (dc, out_row) in out.chunks_exact_mut(tile_rows).enumerate() {
+                            out_row[tr] = in_row[tc0 + dc].transposed();
+                        }
+                    }
+                });
+        }
+        TMat {
+            width: self.height,
+            height: self.width,
+            tiles,
+        }
+    }
+
+    /// Keeps the tile shape. Square tiles just move (see `transpose_reshaped`);
+    /// otherwise each task gathers the output tile rows for one band of input
+    /// columns, walking down the input.
+    pub fn transpose(&self) -> Self {
+        let lth = Tile::<T, LTW>::LTH;
+        if LTW == lth {
+            return self.transpose_reshaped::<LTW>();
+        }
+        let (height, width) = (self.width, self.height);
+        let tpr = width.div_ceil(1 << LTW);
+        let mut tiles = vec![Tile::zero(); height.div_ceil(1 << lth) * tpr];
+        if tpr > 0 {
+            // at least TRANSPOSE_BAND input tile columns and at least one output tile row
+            let band_rows = cmp::max(TRANSPOSE_BAND << LTW, 1 << lth);
+            tiles
+                .par_chunks_mut((band_rows >> lth) * tpr)
+                .enumerate()
+                .for_each(|(band, out)| {
+                    for tc in 0..tpr {
+                        let c0 = tc << LTW;
+                        for (i, out_row) in out.chunks_exact_mut(tpr).enumerate() {
+                            let r0 = band * band_rows + (i << lth);
+                            out_row[tc] =
+                                Tile::from_fn_clipped(height - r0, width - c0, |rit, cit| unsafe {
+                                    self.get_unchecked(c0 + cit, r0 + rit)
+                                });
+                        }
+                    }
+                });
+        }
+        Self {
+            width,
+            height,
+            tiles,
+        }
+    }
+
+    /// Square matrices with square tiles only. Tiles `(i, j)` and `(j, i)` are
+    /// swapped and transposed by the same task, so tasks never touch the same tile.
+    /// Pairs go block by block, `TRANSPOSE_BAND` tiles on a side.
+    pub fn transpose_in_place(&mut self) {
+        assert_eq!(
+            self.height, self.width,
+            "in-place transpose needs a square matrix"
+        );
+        assert_eq!(
+            LTW,
+            Tile::<T, LTW>::LTH,
+            "in-place transpose needs square tiles"
+        );
+        let n = self.tiles_per_row();
+        let tiles = SyncPtr(self.tiles.as_mut_ptr());
+        let blocks = n.div_ceil(TRANSPOSE_BAND);
+        (0..blocks).into_par_iter().for_each(|bi| {
+            for bj in bi..blocks {
+                for i in bi * TRANSPOSE_BAND..cmp::min((bi + 1) * TRANSPOSE_BAND, n) {
+                    let j0 = if bi == bj { i } else { bj * TRANSPOSE_BAND };
+                    for j in j0..cmp::min((bj + 1) * TRANSPOSE_BAND, n) {
+                        unsafe {
+                            let a = tiles.get().add(i * n + j);
+                            let b = tiles.get().add(j * n + i);
+                            let (ta, tb) = ((*a).transposed::<LTW>(), (*b).transposed::<LTW>());
+                            *a = tb;
+                            *b = ta;
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// lets `transpose_in_place` hand one tile buffer to every task
+struct SyncPtr<T>(*mut T);
+
+unsafe impl<T> Send for SyncPtr<T> {}
+unsafe impl<T> Sync for SyncPtr<T> {}
+
+impl<T> SyncPtr<T> {
+    // a method, so closures capture the wrapper rather than the raw pointer
+    fn get(&self) -> *mut T {
+        self.0
+    }
+}
+
+pub struct RowIter<'a, T, const LTW: usize> {
+    tiles: &'a [Tile<T, LTW>],
+    rit: usize,
+    idx: usize,
+    width: usize,
+}
+
+impl<'a, T: Packable, const LTW: usize> Iterator for RowIter<'a, T, LTW> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.idx == self.width {
+            return None;
+        }
+        let t = &self.tiles[self.idx >> LTW];
+        let v = t.as_slice()[(self.rit << LTW) + (self.idx & mask(LTW))];
+        self.idx += 1;
+        Some(v)
+    }
 
-        &mut tile.0[(rit << LTW) + cit]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.width - self.idx;
+        (n, Some(n))
     }
-    */
 }
 
-pub struct TileIter<'t, const I_LTW: usize, const O_LTW: usize> {
+impl<'a, T: Packable, const LTW: usize> ExactSizeIterator for RowIter<'a, T, LTW> {}
+
+pub struct TileIter<'t, T, const I_LTW: usize, const O_LTW: usize> {
     idx: usize,
-    chunk: &'t [Tile<I_LTW>],
-    tile_rows: usize,
+    chunk: &'t [Tile<T, I_LTW>],
+    tiles_per_row: usize,
+    /// logical rows in this band
+    rows: usize,
+    width: usize,
+}
+
+impl<'t, T: Packable, const I_LTW: usize, const O_LTW: usize> TileIter<'t, T, I_LTW, O_LTW> {
+    fn out_tiles_per_row(&self) -> usize {
+        self.width.div_ceil(1 << O_LTW)
+    }
+
+    fn out_tiles(&self) -> usize {
+        self.rows.div_ceil(1 << Tile::<T, O_LTW>::LTH) * self.out_tiles_per_row()
+    }
 }
 
-impl<'t, const I_LTW: usize, const O_LTW: usize> Iterator for TileIter<'t, I_LTW, O_LTW> {
-    type Item = Tile<O_LTW>;
+impl<'t, T: Packable, const I_LTW: usize, const O_LTW: usize> Iterator
+    for TileIter<'t, T, I_LTW, O_LTW>
+{
+    type Item = Tile<T, O_LTW>;
     fn next(&mut self) -> Option<Self::Item> {
-        let o_ltw = 1 << O_LTW;
-        let i_ltw = 1 << I_LTW;
+        if self.idx == self.out_tiles() {
+            return None;
+        }
 
-        let t = Tile::from_fn(|rit, cit| {
-            //
-            0
+        let i_lth = Tile::<T, I_LTW>::LTH;
+        let o_lth = Tile::<T, O_LTW>::LTH;
+        let i_tpr = self.tiles_per_row;
+        let o_tpr = self.out_tiles_per_row();
+        let (otr, otc) = (self.idx / o_tpr, self.idx % o_tpr);
+
+        let (r0, c0) = (otr << o_lth, otc << O_LTW);
+        let t = Tile::from_fn_clipped(self.rows - r0, self.width - c0, |rit, cit| {
+            // position within the band
+            let (r, c) = (r0 + rit, c0 + cit);
+            let tile = &self.chunk[(r >> i_lth) * i_tpr + (c >> I_LTW)];
+            tile.get(r & mask(i_lth), c & mask(I_LTW))
         });
         self.idx += 1;
         Some(t)
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = self.out_tiles() - self.idx;
+        (n, Some(n))
+    }
+}
+
+impl<'t, T: Packable, const I_LTW: usize, const O_LTW: usize> ExactSizeIterator
+    for TileIter<'t, T, I_LTW, O_LTW>
+{
 }
 
-impl<const LTW: usize> fmt::Debug for TMat<LTW> {
+impl<T: Packable + fmt::Debug, const LTW: usize> fmt::Debug for TMat<T, LTW> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f)?;
-        for tile_row in self.tiles.chunks_exact(self.tiles_per_row()) {
-            for tile in tile_row {
+        for tr in 0..self.height.div_ceil(1 << Tile::<T, LTW>::LTH) {
+            for tile in self.tile_row(tr) {
                 write!(f, "[")?;
-                for elt in tile.0 {
-                    write!(f, " {elt:#010x}")?;
+                for elt in tile.as_slice() {
+                    write!(f, " {elt:?}")?;
                 }
                 write!(f, "]")?;
             }
@@ -166,14 +730,394 @@ impl<const LTW: usize> fmt::Debug for TMat<LTW> {
 
 #[cfg(test)]
 mod tests {
+    use p3_field::extension::Complex;
+    use p3_mersenne_31::Mersenne31;
+    use rand::{
+        distributions::{Distribution, Standard},
+        Rng, SeedableRng,
+    };
+    use rand_chacha::ChaChaRng;
+
     use super::*;
 
     #[test]
     fn it_works() {
         let (log_h, log_w) = (4, 4);
-        let m = TMat::<3>::from_fn(1 << log_h, 1 << log_w, |r, c| ((r << log_w) + c) as u32);
+        let m = TMat::<u32, 3>::from_fn(1 << log_h, 1 << log_w, |r, c| ((r << log_w) + c) as u32);
         dbg!(&m);
 
         // assert_eq!(1, 2);
     }
+
+    #[test]
+    fn wrapping_add_matches_scalar() {
+        let a = Tile::<u32, 2>::from_fn(|r, c| u32::MAX - (r * 4 + c) as u32);
+        let b = Tile::<u32, 2>::from_fn(|r, c| (r * 7 + c * 3) as u32);
+        let mut sum = a;
+        sum.wrapping_add_assign(&b);
+        for i in 0..16 {
+            assert_eq!(
+                sum.as_slice()[i],
+                a.as_slice()[i].wrapping_add(b.as_slice()[i])
+            );
+        }
+    }
+
+    #[test]
+    fn m31_mul_assign_matches_scalar() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let a = Tile::<M31, 2>::from_fn(|_, _| rng.gen());
+        let b = Tile::<M31, 2>::from_fn(|_, _| rng.gen());
+        let mut prod = a;
+        prod.mul_assign(&b);
+        for (i, x) in prod.as_slice().iter().enumerate() {
+            assert_eq!(*x, a.as_slice()[i] * b.as_slice()[i]);
+        }
+    }
+
+    fn check_access<T, const LTW: usize>(rng: &mut impl Rng)
+    where
+        T: Packable + PartialEq + fmt::Debug,
+        Standard: Distribution<T>,
+    {
+        let h = rng.gen_range(1..100);
+        let w = rng.gen_range(1..100);
+        let mut naive: Vec<T> = (0..h * w).map(|_| rng.gen()).collect();
+        let mut m = TMat::<T, LTW>::from_fn(h, w, |r, c| naive[r * w + c]);
+        assert_eq!((m.height(), m.width), (h, w));
+
+        for _ in 0..64 {
+            let (r, c) = (rng.gen_range(0..h), rng.gen_range(0..w));
+            let val = rng.gen();
+            naive[r * w + c] = val;
+            m.set(r, c, val);
+        }
+
+        for r in 0..h {
+            for c in 0..w {
+                assert_eq!(m.get(r, c), naive[r * w + c]);
+            }
+            assert!(m.row(r).eq(naive[r * w..(r + 1) * w].iter().copied()));
+        }
+        for c in 0..w {
+            assert!(m.col(c).eq(naive[c..].iter().step_by(w).copied()));
+        }
+    }
+
+    #[test]
+    fn element_access_matches_naive() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        for _ in 0..16 {
+            check_access::<u32, 0>(&mut rng);
+            check_access::<u32, 1>(&mut rng);
+            check_access::<u32, 2>(&mut rng);
+            check_access::<u32, 3>(&mut rng);
+            check_access::<u32, 4>(&mut rng);
+        }
+    }
+
+    #[test]
+    fn element_types() {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        check_access::<u8, 0>(&mut rng);
+        check_access::<u8, 3>(&mut rng);
+        check_access::<u8, 6>(&mut rng);
+        check_access::<u16, 5>(&mut rng);
+        check_access::<u64, 1>(&mut rng);
+        check_access::<u64, 3>(&mut rng);
+        check_access::<Mersenne31, 2>(&mut rng);
+        check_access::<Complex<Mersenne31>, 0>(&mut rng);
+        check_access::<Complex<Mersenne31>, 3>(&mut rng);
+
+        assert_eq!(Tile::<u8, 2>::LTH, 4);
+        assert_eq!(Tile::<u64, 2>::LTH, 1);
+        assert_eq!(Tile::<Complex<Mersenne31>, 1>::LTH, 2);
+    }
+
+    #[test]
+    fn ragged_round_trip() {
+        let (h, w) = (1000, 37);
+        let f = |r: usize, c: usize| (r * w + c) as u32;
+        let m = TMat::<u32, 3>::from_fn(h, w, f);
+        assert_eq!((m.height(), m.width), (h, w));
+        for r in 0..h {
+            assert!(m.row(r).eq((0..w).map(|c| f(r, c))));
+            assert_eq!(m.row_segments(r).map(<[u32]>::len).sum::<usize>(), w);
+        }
+        for c in 0..w {
+            assert!(m.col(c).eq((0..h).map(|r| f(r, c))));
+        }
+    }
+
+    #[test]
+    fn zero_width() {
+        let mut m = TMat::<M31, 2>::from_fn(5, 0, |_, _| unreachable!());
+        assert_eq!(m.fold_rows(|rows| rows, |rows, _| rows), [0..4, 4..5]);
+        assert_eq!(m.par_row_tiles_native().count(), 2);
+        assert_eq!(m.par_row_tiles_native_mut().count(), 2);
+        assert!(m
+            .par_row_tiles::<0>()
+            .all(|mut tiles| tiles.next().is_none()));
+        assert_eq!(format!("{m:?}"), "\n\n\n");
+    }
+
+    #[test]
+    fn zero_height() {
+        let m = TMat::<M31, 2>::from_fn(0, 37, |_, _| unreachable!());
+        assert!((0..37).all(|c| m.col(c).next().is_none()));
+        assert_eq!(m.fold_cols(|cols| cols, |cols, _| cols).len(), 10);
+    }
+
+    #[test]
+    #[should_panic]
+    fn get_out_of_bounds() {
+        let m = TMat::<u32, 2>::zero(8, 8);
+        m.get(8, 0);
+    }
+
+    #[test]
+    fn fold_rows_gets_row_ranges() {
+        let (h, w) = (37, 11);
+        let f = |r: usize, c: usize| (r * 1000 + c) as u32;
+        let m = TMat::<u32, 2>::from_fn(h, w, f);
+        let bands = m.fold_rows(
+            |rows| (rows, 0u32),
+            |(rows, acc), tile| {
+                (
+                    rows,
+                    tile.as_slice().iter().fold(acc, |a, x| a.wrapping_add(*x)),
+                )
+            },
+        );
+        let mut next = 0;
+        for (rows, sum) in bands {
+            assert_eq!(rows.start, next);
+            let expected = iproduct!(rows.clone(), 0..w).map(|(r, c)| f(r, c)).sum();
+            assert_eq!(sum, expected);
+            next = rows.end;
+        }
+        assert_eq!(next, h);
+    }
+
+    #[test]
+    fn zip_and_map_tiles() {
+        let (h, w) = (37, 11);
+        let mut a = TMat::<u32, 3>::from_fn(h, w, |r, c| (r * w + c) as u32);
+        let b = TMat::<u32, 3>::from_fn(h, w, |r, c| (r + 3 * c) as u32);
+        a.zip_tiles_mut(&b, |x, y| {
+            for (x, y) in x.as_mut_slice().iter_mut().zip(y.as_slice()) {
+                *x = x.wrapping_mul(*y);
+            }
+        });
+        let a = a.map_tiles(|t| {
+            let mut doubled = *t;
+            doubled.wrapping_add_assign(t);
+            doubled
+        });
+        for (r, c) in iproduct!(0..h, 0..w) {
+            assert_eq!(a.get(r, c), 2 * (r * w + c) as u32 * (r + 3 * c) as u32);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn zip_tiles_dimension_mismatch() {
+        let mut a = TMat::<u32, 2>::zero(8, 8);
+        a.zip_tiles_mut(&TMat::zero(8, 12), |_, _| {});
+    }
+
+    fn check_fold_cols<const LTW: usize>(h: usize, w: usize) {
+        let f = |r: usize, c: usize| (r * 1000 + c) as u32;
+        let m = TMat::<u32, LTW>::from_fn(h, w, f);
+        let strips = m.fold_cols(
+            |cols| (cols, Tile::<u32, LTW>::zero()),
+            |(cols, mut acc), tile| {
+                acc.wrapping_add_assign(tile);
+                (cols, acc)
+            },
+        );
+        assert_eq!(strips.len(), w.div_ceil(1 << LTW));
+
+        let mut sums: Vec<u32> = vec![];
+        for (cols, acc) in strips {
+            assert_eq!(cols.start, sums.len());
+            for cit in 0..cols.len() {
+                sums.push(
+                    (0..1 << Tile::<u32, LTW>::LTH)
+                        .map(|rit| acc.get(rit, cit))
+                        .sum(),
+                );
+            }
+        }
+        let expected = (0..w)
+            .map(|c| (0..h).map(|r| f(r, c)).sum())
+            .collect::<Vec<u32>>();
+        assert_eq!(sums, expected, "LTW = {LTW}, {h}x{w}");
+    }
+
+    #[test]
+    fn fold_cols_sums_columns() {
+        check_fold_cols::<0>(64, 16);
+        check_fold_cols::<2>(64, 16);
+        check_fold_cols::<4>(64, 16);
+        check_fold_cols::<1>(37, 11);
+        check_fold_cols::<3>(37, 11);
+        check_fold_cols::<4>(1000, 37);
+    }
+
+    #[test]
+    fn tile_transpose_matches_naive() {
+        let t = Tile::<u32, 2>::from_fn(|r, c| (r * 4 + c) as u32);
+        let tt = t.transposed::<2>();
+        let t8 = Tile::<u8, 2>::from_fn(|r, c| (r * 4 + c) as u8);
+        let tt8 = t8.transposed::<4>();
+        for (r, c) in iproduct!(0..4, 0..4) {
+            assert_eq!(tt.get(c, r), t.get(r, c));
+        }
+        for (r, c) in iproduct!(0..16, 0..4) {
+            assert_eq!(tt8.get(c, r), t8.get(r, c));
+        }
+        let row = Tile::<u32, 4>::from_fn(|_, c| c as u32);
+        assert_eq!(row.transposed::<0>().as_slice(), row.as_slice());
+    }
+
+    fn check_transpose<T, const LTW: usize>(h: usize, w: usize)
+    where
+        T: Packable + PartialEq + fmt::Debug,
+        Standard: Distribution<T>,
+    {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let naive: Vec<T> = (0..h * w).map(|_| rng.gen()).collect();
+        let m = TMat::<T, LTW>::from_fn(h, w, |r, c| naive[r * w + c]);
+        let expected = TMat::<T, LTW>::from_fn(w, h, |r, c| naive[c * w + r]);
+        let t = m.transpose();
+        assert_eq!((t.height, t.width), (w, h));
+        // padding included, it has to come out zero
+        assert_eq!(t.tiles, expected.tiles, "LTW = {LTW}, {h}x{w}");
+
+        if h == w && LTW == Tile::<T, LTW>::LTH {
+            let mut m = m;
+            m.transpose_in_place();
+            assert_eq!(m.tiles, expected.tiles, "in place, LTW = {LTW}, {h}x{w}");
+        }
+    }
+
+    #[test]
+    fn transpose_matches_naive() {
+        for (h, w) in [
+            (64, 64),
+            (37, 11),
+            (11, 300),
+            (37, 37),
+            (150, 150),
+            (300, 70),
+        ] {
+            check_transpose::<u32, 0>(h, w);
+            check_transpose::<u32, 2>(h, w);
+            check_transpose::<u32, 3>(h, w);
+            check_transpose::<u8, 3>(h, w);
+            check_transpose::<u64, 1>(h, w);
+            check_transpose::<Mersenne31, 2>(h, w);
+        }
+    }
+
+    fn check_transpose_reshaped<const I_LTW: usize, const O_LTW: usize>() {
+        // the second size spans several transpose bands
+        for (h, w) in [(37, 70), (300, 150)] {
+            let f = |r: usize, c: usize| (r * 1000 + c) as u32;
+            let m = TMat::<u32, I_LTW>::from_fn(h, w, f);
+            let expected = TMat::<u32, O_LTW>::from_fn(w, h, |r, c| f(c, r));
+            assert_eq!(m.transpose_reshaped::<O_LTW>().tiles, expected.tiles);
+        }
+    }
+
+    #[test]
+    fn transpose_reshaped_matches_naive() {
+        check_transpose_reshaped::<4, 0>();
+        check_transpose_reshaped::<0, 4>();
+        check_transpose_reshaped::<1, 3>();
+        check_transpose_reshaped::<3, 1>();
+        check_transpose_reshaped::<2, 2>();
+    }
+
+    fn check_retile<const I_LTW: usize, const O_LTW: usize>() {
+        let (log_h, log_w) = (6, 5);
+        let f = |r: usize, c: usize| ((r << log_w) + c) as u32;
+        let m = TMat::<u32, I_LTW>::from_fn(1 << log_h, 1 << log_w, f);
+
+        let bands = m.par_row_tiles::<O_LTW>().collect::<Vec<_>>();
+        for band in &bands {
+            assert_eq!(band.len(), band.chunk.len());
+        }
+        let retiled = bands.into_iter().flatten().collect::<Vec<_>>();
+
+        let expected = TMat::<u32, O_LTW>::from_fn(1 << log_h, 1 << log_w, f);
+        assert_eq!(retiled, expected.tiles, "{I_LTW} -> {O_LTW}");
+
+        // ragged: output padding has to come out as zeros
+        let (h, w) = (37, 11);
+        let m = TMat::<u32, I_LTW>::from_fn(h, w, f);
+        let retiled = m
+            .par_row_tiles::<O_LTW>()
+            .flatten_iter()
+            .collect::<Vec<_>>();
+        let expected = TMat::<u32, O_LTW>::from_fn(h, w, f);
+        assert_eq!(retiled, expected.tiles, "{I_LTW} -> {O_LTW}, ragged");
+
+        for (h, w) in [(64, 32), (37, 11), (300, 150), (1, 70)] {
+            let m = TMat::<u32, I_LTW>::from_fn(h, w, f);
+            let expected = TMat::<u32, O_LTW>::from_fn(h, w, f);
+            let retiled = m.retile::<O_LTW>();
+            assert_eq!((retiled.height, retiled.width), (h, w));
+            assert_eq!(
+                retiled.tiles, expected.tiles,
+                "retile {I_LTW} -> {O_LTW}, {h}x{w}"
+            );
+        }
+    }
+
+    macro_rules! check_retile_all {
+        ($($i:literal),*) => {
+            $(
+                check_retile::<$i, 0>();
+                check_retile::<$i, 1>();
+                check_retile::<$i, 2>();
+                check_retile::<$i, 3>();
+                check_retile::<$i, 4>();
+            )*
+        };
+    }
+
+    #[test]
+    fn par_row_tiles_round_trip() {
+        check_retile_all!(0, 1, 2, 3, 4);
+    }
+
+    fn check_retile_other<T, const I_LTW: usize, const O_LTW: usize>()
+    where
+        T: Packable + PartialEq + fmt::Debug,
+        Standard: Distribution<T>,
+    {
+        let (h, w) = (150, 70);
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let vals: Vec<T> = (0..h * w).map(|_| rng.gen()).collect();
+        let f = |r: usize, c: usize| vals[r * w + c];
+        let m = TMat::<T, I_LTW>::from_fn(h, w, f);
+        let expected = TMat::<T, O_LTW>::from_fn(h, w, f);
+        assert_eq!(
+            m.retile::<O_LTW>().tiles,
+            expected.tiles,
+            "{I_LTW} -> {O_LTW}"
+        );
+    }
+
+    #[test]
+    fn retile_other_sizes() {
+        check_retile_other::<u8, 6, 0>();
+        check_retile_other::<u8, 1, 5>();
+        check_retile_other::<u64, 0, 3>();
+        check_retile_other::<u64, 2, 1>();
+        check_retile_other::<Mersenne31, 4, 0>();
+        check_retile_other::<Mersenne31, 0, 2>();
+    }
 }
@@ -1,17 +1,39 @@
-trait Packable {}
+//! Bare-bones byte-backed tiles: no padding, no iterators, just scalar loads and
+//! stores, as a reference point for `TMat`.
+
+use std::{marker::PhantomData, mem, ptr};
+
+use crate::packable::Packable;
 
 #[derive(Copy, Clone, Debug)]
 #[repr(align(64))]
-struct Tile<T, const LTW: usize>([u8; 64], PhantomData<T>);
+pub struct Tile<T, const LTW: usize>([u8; 64], PhantomData<T>);
+
+/// a full-width row for 4-byte elements
+pub type RowTile<T> = Tile<T, 4>;
+pub type ColTile<T> = Tile<T, 0>;
 
-type RowTile<T> = Tile<T, 4>;
-type ColTile<T> = Tile<T, 0>;
+impl<T: Packable, const LTW: usize> Tile<T, LTW> {
+    pub const LTH: usize = 6 - (mem::size_of::<T>().ilog2() as usize) - LTW;
 
-impl<T, const LTW: usize> Tile<T, LTW> {
-    const LTH: usize = 6 - (mem::size_of::<T>().ilog2() as usize) - LTW;
-    fn zero() -> Self {
+    pub fn zero() -> Self {
         Tile([0; 64], PhantomData)
     }
+
+    /// row-major within the tile
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.0.as_ptr() as *const T, 1 << (Self::LTH + LTW)) }
+    }
+
+    fn ptr(&self, rit: usize, cit: usize) -> *const T {
+        debug_assert!(rit < (1 << Self::LTH) && cit < (1 << LTW));
+        unsafe { (self.0.as_ptr() as *const T).add((rit << LTW) + cit) }
+    }
+
+    fn ptr_mut(&mut self, rit: usize, cit: usize) -> *mut T {
+        debug_assert!(rit < (1 << Self::LTH) && cit < (1 << LTW));
+        unsafe { (self.0.as_mut_ptr() as *mut T).add((rit << LTW) + cit) }
+    }
 }
 
 const _: () = {
@@ -19,21 +41,125 @@ const _: () = {
     assert!(mem::align_of::<Tile<(), 0>>() == 64);
 };
 
-struct Mat<T, const LTW: usize> {
+pub struct Mat<T, const LTW: usize> {
     tiles: Vec<Tile<T, LTW>>,
     tiles_per_row: usize,
 }
 
 impl<T, const LTW: usize> Mat<T, LTW>
 where
-    T: Copy,
+    T: Packable,
 {
-    fn load_scalar(&self, r: usize, c: usize) -> T {
+    /// dimensions must be whole tiles
+    pub fn zero(height: usize, width: usize) -> Self {
+        let lth = Tile::<T, LTW>::LTH;
+        let (th, tw) = (1 << lth, 1 << LTW);
+        assert_eq!(height % th, 0, "height must be a multiple of {th}");
+        assert_eq!(width % tw, 0, "width must be a multiple of {tw}");
+        Self {
+            tiles: vec![Tile::zero(); (height >> lth) * (width >> LTW)],
+            tiles_per_row: width >> LTW,
+        }
+    }
+
+    pub fn from_fn(height: usize, width: usize, mut f: impl FnMut(usize, usize) -> T) -> Self {
+        let mut m = Self::zero(height, width);
+        for r in 0..height {
+            for c in 0..width {
+                m.store_scalar(r, c, f(r, c));
+            }
+        }
+        m
+    }
+
+    pub fn width(&self) -> usize {
+        self.tiles_per_row << LTW
+    }
+
+    pub fn height(&self) -> usize {
+        match self.tiles_per_row {
+            0 => 0,
+            tpr => (self.tiles.len() / tpr) << Tile::<T, LTW>::LTH,
+        }
+    }
+
+    pub fn tiles(&self) -> &[Tile<T, LTW>] {
+        &self.tiles
+    }
+
+    fn tile_index(&self, r: usize, c: usize) -> usize {
+        assert!(r < self.height() && c < self.width());
         let tr = r >> Tile::<T, LTW>::LTH;
         let tc = c >> LTW;
-        let t = &self.tiles[(self.tiles_per_row * tr) + tc];
-        // let s = slice::from_raw_parts(t.0.as_slice(), 1 << ())
+        (self.tiles_per_row * tr) + tc
+    }
+
+    pub fn load_scalar(&self, r: usize, c: usize) -> T {
+        let lth = Tile::<T, LTW>::LTH;
+        let t = &self.tiles[self.tile_index(r, c)];
+        unsafe { ptr::read(t.ptr(r & ((1 << lth) - 1), c & ((1 << LTW) - 1))) }
+    }
+
+    pub fn store_scalar(&mut self, r: usize, c: usize, val: T) {
+        let lth = Tile::<T, LTW>::LTH;
+        let i = self.tile_index(r, c);
+        let t = &mut self.tiles[i];
+        unsafe { ptr::write(t.ptr_mut(r & ((1 << lth) - 1), c & ((1 << LTW) - 1)), val) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use rand::{
+        distributions::{Distribution, Standard},
+        Rng, SeedableRng,
+    };
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+    use crate::tiled_mat::TMat;
+
+    fn check<T, const LTW: usize>(height: usize, width: usize)
+    where
+        T: Packable + PartialEq + Debug,
+        Standard: Distribution<T>,
+    {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let naive: Vec<T> = (0..height * width).map(|_| rng.gen()).collect();
+        let mut m = Mat::<T, LTW>::from_fn(height, width, |r, c| naive[r * width + c]);
+        let tm = TMat::<T, LTW>::from_fn(height, width, |r, c| naive[r * width + c]);
+        assert_eq!((m.height(), m.width()), (height, width));
+
+        for r in 0..height {
+            for c in 0..width {
+                assert_eq!(m.load_scalar(r, c), naive[r * width + c]);
+                assert_eq!(m.load_scalar(r, c), tm.get(r, c));
+            }
+        }
+
+        let (r, c) = (height - 1, width - 1);
+        let val = rng.gen();
+        m.store_scalar(r, c, val);
+        assert_eq!(m.load_scalar(r, c), val);
+    }
+
+    #[test]
+    fn load_store() {
+        check::<u32, 2>(16, 16);
+        check::<u8, 5>(8, 64);
+        check::<u64, 0>(32, 4);
+    }
 
-        todo!()
+    #[test]
+    fn row_and_col_tiles() {
+        assert_eq!(RowTile::<u32>::LTH, 0);
+        assert_eq!(ColTile::<u32>::LTH, 4);
+        let mut m = Mat::<u32, 4>::zero(4, 32);
+        m.store_scalar(3, 17, 7);
+        // one tile per row, two per tile row
+        assert_eq!(m.tiles().len(), 8);
+        assert_eq!(m.load_scalar(3, 17), 7);
     }
 }
@@ -1,28 +1,117 @@
-use std::ops::{Add, AddAssign};
+//! A minimal Mersenne-31 field element, so the layouts can be benchmarked without
+//! going through `p3_field`'s trait stack.
 
-#[derive(Copy, Clone, Default)]
+use std::{
+    cmp, fmt,
+    iter::{Product, Sum},
+    ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+use rand::{
+    distributions::{Distribution, Standard},
+    Rng,
+};
+
+/// Always canonical, i.e. in `0..P`.
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash)]
 #[repr(transparent)]
 pub struct M31(u32);
 
 impl M31 {
-    const P: u32 = (1 << 31) - 1;
+    pub const P: u32 = (1 << 31) - 1;
+
+    pub const ZERO: Self = Self(0);
+    pub const ONE: Self = Self(1);
+    pub const TWO: Self = Self(2);
+    pub const NEG_ONE: Self = Self(Self::P - 1);
+
+    /// `x` must already be reduced
+    #[inline]
+    pub const fn from_canonical(x: u32) -> Self {
+        debug_assert!(x < Self::P);
+        Self(x)
+    }
+
+    #[inline]
+    pub const fn from_wrapped_u32(x: u32) -> Self {
+        // x < 2^32, so after one fold it's at most P + 1
+        Self(Self::reduce_small((x & Self::P) + (x >> 31)))
+    }
+
+    #[inline]
+    pub const fn from_wrapped_u64(x: u64) -> Self {
+        let p = Self::P as u64;
+        let x = (x & p) + (x >> 31);
+        let x = (x & p) + (x >> 31);
+        Self(Self::reduce_small(x as u32))
+    }
+
+    #[inline]
+    pub const fn value(self) -> u32 {
+        self.0
+    }
+
+    /// maps `0..2P` onto `0..P` with a select rather than a branch: if `x < P`,
+    /// `x - P` wraps around and the min picks `x`
+    #[inline(always)]
+    const fn reduce_small(x: u32) -> u32 {
+        let y = x.wrapping_sub(Self::P);
+        if x < y {
+            x
+        } else {
+            y
+        }
+    }
+
+    #[inline]
+    pub fn double(self) -> Self {
+        self + self
+    }
+
+    #[inline]
+    pub fn square(self) -> Self {
+        self * self
+    }
+
+    pub fn pow(self, mut exp: u64) -> Self {
+        let (mut base, mut acc) = (self, Self::ONE);
+        while exp != 0 {
+            if exp & 1 == 1 {
+                acc *= base;
+            }
+            base = base.square();
+            exp >>= 1;
+        }
+        acc
+    }
+
+    /// `None` for zero
+    pub fn try_inverse(self) -> Option<Self> {
+        (self != Self::ZERO).then(|| self.pow(Self::P as u64 - 2))
+    }
+
+    pub fn inverse(self) -> Self {
+        self.try_inverse().expect("inverse of zero")
+    }
 }
 
 impl From<u32> for M31 {
     fn from(value: u32) -> Self {
-        Self(value % Self::P)
+        Self::from_wrapped_u32(value)
     }
 }
 impl From<u64> for M31 {
     fn from(value: u64) -> Self {
-        Self((value % (Self::P as u64)) as u32)
+        Self::from_wrapped_u64(value)
     }
 }
 
 impl Add for M31 {
     type Output = Self;
+    #[inline]
     fn add(self, rhs: Self) -> Self {
-        ((self.0 as u64) + (rhs.0 as u64)).into()
+        // both < P, so the sum fits in a u32
+        Self(Self::reduce_small(self.0 + rhs.0))
     }
 }
 impl AddAssign for M31 {
@@ -30,3 +119,143 @@ impl AddAssign for M31 {
         *self = *self + rhs;
     }
 }
+
+impl Sub for M31 {
+    type Output = Self;
+    #[inline]
+    fn sub(self, rhs: Self) -> Self {
+        // on underflow the difference wraps past 2^32, and adding P brings it back
+        let d = self.0.wrapping_sub(rhs.0);
+        Self(cmp::min(d, d.wrapping_add(Self::P)))
+    }
+}
+impl SubAssign for M31 {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for M31 {
+    type Output = Self;
+    #[inline]
+    fn neg(self) -> Self {
+        Self::ZERO - self
+    }
+}
+
+impl Mul for M31 {
+    type Output = Self;
+    #[inline]
+    fn mul(self, rhs: Self) -> Self {
+        // 2^31 = 1 mod P, so the high bits fold back onto the low ones
+        let prod = (self.0 as u64) * (rhs.0 as u64);
+        let lo = (prod as u32) & Self::P;
+        let hi = (prod >> 31) as u32;
+        Self(Self::reduce_small(lo + hi))
+    }
+}
+impl MulAssign for M31 {
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl Sum for M31 {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, |a, b| a + b)
+    }
+}
+impl Product for M31 {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ONE, |a, b| a * b)
+    }
+}
+
+impl fmt::Display for M31 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+impl fmt::Debug for M31 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0, f)
+    }
+}
+
+impl Distribution<M31> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> M31 {
+        M31(rng.gen_range(0..M31::P))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_field::{AbstractField, Field, PrimeField32};
+    use p3_mersenne_31::Mersenne31;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    use super::*;
+
+    fn p3(x: M31) -> Mersenne31 {
+        Mersenne31::from_canonical_u32(x.value())
+    }
+
+    /// random elements plus the edges
+    fn samples() -> Vec<M31> {
+        let mut rng = ChaChaRng::seed_from_u64(0);
+        let mut xs = vec![M31::ZERO, M31::ONE, M31::TWO, M31::NEG_ONE, M31(1 << 30)];
+        xs.extend((0..200).map(|_| rng.gen::<M31>()));
+        xs
+    }
+
+    #[test]
+    fn matches_p3() {
+        let xs = samples();
+        for &a in &xs {
+            assert_eq!(p3(-a), -p3(a));
+            assert_eq!(p3(a.double()), p3(a).double());
+            assert_eq!(p3(a.square()), p3(a).square());
+            assert_eq!(p3(a.pow(12345)), p3(a).exp_u64(12345));
+            assert_eq!(a.try_inverse().map(p3), p3(a).try_inverse());
+            for &b in &xs {
+                assert_eq!(p3(a + b), p3(a) + p3(b), "{a} + {b}");
+                assert_eq!(p3(a - b), p3(a) - p3(b), "{a} - {b}");
+                assert_eq!(p3(a * b), p3(a) * p3(b), "{a} * {b}");
+            }
+        }
+        assert_eq!(
+            p3(xs.iter().copied().sum()),
+            xs.iter().copied().map(p3).sum()
+        );
+        assert_eq!(
+            p3(xs[1..].iter().copied().product()),
+            xs[1..].iter().copied().map(p3).product()
+        );
+    }
+
+    #[test]
+    fn wrapped_constructors() {
+        let mut rng = ChaChaRng::seed_from_u64(1);
+        let edges32 = [0, 1, M31::P - 1, M31::P, M31::P + 1, u32::MAX];
+        for x in edges32.into_iter().chain((0..1000).map(|_| rng.gen())) {
+            let m = M31::from_wrapped_u32(x);
+            assert_eq!(m.value(), x % M31::P);
+            assert_eq!(
+                m.value(),
+                Mersenne31::from_wrapped_u32(x).as_canonical_u32()
+            );
+        }
+        let p = M31::P as u64;
+        let edges64 = [0, p - 1, p, p * p, u64::MAX - 1, u64::MAX];
+        for x in edges64.into_iter().chain((0..1000).map(|_| rng.gen())) {
+            assert_eq!(M31::from_wrapped_u64(x).value() as u64, x % p);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn inverse_of_zero() {
+        M31::ZERO.inverse();
+    }
+}